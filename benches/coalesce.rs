@@ -0,0 +1,33 @@
+//! [`Tec::coalesce()`] versus [`Tec::par_coalesce()`] on large stores with many holes. Needs `--features parallel`.
+
+mod util;
+
+use stable_id::Tec;
+
+/// A store of `capacity` slots where every other slot in the first half is dead,
+/// so that the living items of the second half have to fill them.
+fn holey_tec(capacity: u32) -> Tec<u32, [u64; 4]> {
+    let mut tec = Tec::populate([0; 4], capacity as usize);
+    (0..capacity / 2).step_by(2).for_each(|id| {
+        tec.remove(id);
+    });
+    tec
+}
+
+fn main() {
+    for capacity in [10_000, 100_000, 1_000_000] {
+        let tec = holey_tec(capacity);
+
+        util::bench(&format!("coalesce, {} slots", capacity), || {
+            let mut tec = tec.clone();
+            tec.coalesce(|_, _| {});
+            tec
+        });
+        util::bench(&format!("par_coalesce, {} slots", capacity), || {
+            let mut tec = tec.clone();
+            tec.par_coalesce(|_, _| {});
+            tec
+        });
+        util::bench(&format!("clone only, {} slots", capacity), || tec.clone());
+    }
+}
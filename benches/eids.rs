@@ -0,0 +1,48 @@
+//! The bitset that [`Eids`] picks for small id types, versus the B-tree that bigger id types get,
+//! on the same claim/unclaim workload.
+
+mod util;
+
+use stable_id::{CastUsize, Eids, EidsIndex};
+
+/// Claim `count` ids, then free every `stride`-th one.
+fn churned<IndexT>(count: usize, stride: usize) -> Eids<IndexT>
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    let mut entities = Eids::default();
+    let ids: Vec<_> = (0..count).map(|_| entities.claim()).collect();
+    ids.into_iter()
+        .step_by(stride)
+        .for_each(|id| entities.unclaim(id));
+    entities
+}
+
+fn run<IndexT>(name: &str)
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    let mut entities = churned::<IndexT>(60_000, 2);
+    util::bench(&format!("{}: claim + unclaim, 30k freed", name), || {
+        let id = entities.claim();
+        entities.unclaim(id);
+        id
+    });
+
+    util::bench(&format!("{}: claim 1k, then unclaim them", name), || {
+        let ids: Vec<_> = (0..1000).map(|_| entities.claim()).collect();
+        ids.into_iter().for_each(|id| entities.unclaim(id));
+    });
+
+    let entities = churned::<IndexT>(60_000, 2);
+    util::bench(&format!("{}: coalesce 30k freed", name), || {
+        let mut entities = entities.clone();
+        entities.coalesce(|_, _| {});
+        entities
+    });
+}
+
+fn main() {
+    run::<u16>("bitset (u16)");
+    run::<u32>("b-tree (u32)");
+}
@@ -0,0 +1,30 @@
+//! Walking an [`Entities`] by its vtable versus in physical order, where the virtual ids are looked up per item.
+
+mod util;
+
+use stable_id::Entities;
+
+fn main() {
+    for len in [1_000, 100_000, 1_000_000] {
+        let mut entities: Entities<u32, u64> = Default::default();
+        let ids: Vec<_> = (0..len as u64).map(|i| entities.alloc(i)).collect();
+        // move the tail items into the holes, so the physical order differs from the virtual one
+        ids.iter().step_by(3).for_each(|&id| {
+            entities.remove(id);
+        });
+
+        util::bench(&format!("iter_with_id, {} items", len), || {
+            entities.iter_with_id().fold(0, |acc, (_, data)| acc ^ data)
+        });
+        util::bench(&format!("iter_with_id_physical, {} items", len), || {
+            entities
+                .iter_with_id_physical()
+                .fold(0, |acc, (_, data)| acc ^ data)
+        });
+        util::bench(&format!("for_each_physical, {} items", len), || {
+            let mut acc = 0;
+            entities.for_each_physical(|_, data| acc ^= data);
+            acc
+        });
+    }
+}
@@ -0,0 +1,48 @@
+//! Iterating a [`Tec`] where most slots are dead. Run once with and once without `--features fast-iter` to compare
+//! scanning every slot against jumping between the living ones.
+
+mod util;
+
+use stable_id::Tec;
+
+/// A store of `capacity` slots where only every `stride`-th one is alive.
+fn sparse_tec(capacity: u32, stride: u32) -> Tec<u32, u64> {
+    let mut tec = Tec::populate(0, capacity as usize);
+    (0..capacity).filter(|id| id % stride != 0).for_each(|id| {
+        tec.remove(id);
+    });
+    tec
+}
+
+fn main() {
+    let features = if cfg!(feature = "fast-iter") {
+        "fast-iter"
+    } else {
+        "scan"
+    };
+
+    for stride in [1, 10, 100, 1000] {
+        let mut tec = sparse_tec(1_000_000, stride);
+
+        util::bench(
+            &format!("{}: iter_with_id, 1 in {} alive", features, stride),
+            || tec.iter_with_id().fold(0, |acc, (id, _)| acc ^ id),
+        );
+        util::bench(
+            &format!("{}: iter_mut, 1 in {} alive", features, stride),
+            || tec.iter_mut().fold(0, |acc, data| acc ^ *data),
+        );
+        util::bench(
+            &format!("{}: iter_from, 1 in {} alive", features, stride),
+            || tec.iter_from(500_000).fold(0, |acc, (id, _)| acc ^ id),
+        );
+        util::bench(
+            &format!("{}: split_at_mut, 1 in {} alive", features, stride),
+            || {
+                let (mut low, mut high) = tec.split_at_mut(500_000);
+                low.iter_mut().fold(0, |acc, data| acc ^ *data)
+                    ^ high.iter_mut().fold(0, |acc, data| acc ^ *data)
+            },
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! [`Tec::live_ids_cached()`] versus filtering the slots on every pass, on stores where most slots are dead.
+
+mod util;
+
+use stable_id::Tec;
+
+/// A store of `capacity` slots where only every `stride`-th one is alive.
+fn sparse_tec(capacity: u32, stride: u32) -> Tec<u32, u64> {
+    let mut tec = Tec::populate(0, capacity as usize);
+    (0..capacity).filter(|id| id % stride != 0).for_each(|id| {
+        tec.remove(id);
+    });
+    tec
+}
+
+fn main() {
+    for stride in [1, 10, 100, 1000] {
+        let tec = sparse_tec(1_000_000, stride);
+
+        util::bench(&format!("iter_with_id, 1 in {} alive", stride), || {
+            tec.iter_with_id()
+                .map(|(id, _)| id)
+                .fold(0, u32::wrapping_add)
+        });
+        util::bench(&format!("live_ids_cached, 1 in {} alive", stride), || {
+            tec.live_ids_cached()
+                .iter()
+                .copied()
+                .fold(0, u32::wrapping_add)
+        });
+    }
+}
@@ -0,0 +1,26 @@
+//! A tiny timing harness, so that the benchmarks don't pull in any extra dependency. Run them with `cargo bench`,
+//! optionally with the features being compared, e.g. `cargo bench --features fast-iter`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const BUDGET: Duration = Duration::from_millis(500);
+
+/// Call `f` repeatedly for about half a second and print the average time per call.
+pub fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    black_box(f()); // warm up
+
+    let start = Instant::now();
+    let mut iters = 0;
+    while start.elapsed() < BUDGET {
+        black_box(f());
+        iters += 1;
+    }
+
+    println!(
+        "{:<48} {:>12.2?}/iter ({} iters)",
+        name,
+        start.elapsed() / iters,
+        iters
+    );
+}
@@ -0,0 +1,37 @@
+/**
+Declare an id tuple struct that works with every collection of this crate, i.e. the boilerplate shown in the crate docs.
+The struct gets the [`crate::StableId`] derive along with [`Debug`], and the inner value is public.
+Note: like the derive itself, the generated code refers to the `stable_id_traits` crate, so it needs to be a dependency.
+
+```
+stable_id::define_id!(pub EntityId: u32);
+stable_id::define_id!(
+    /// Attributes and doc comments are passed along.
+    ItemId: u8
+);
+
+let mut entities: stable_id::Tec<EntityId, &str> = Default::default();
+assert_eq!(entities.alloc("player"), EntityId(0));
+
+let mut items: stable_id::Eids<ItemId> = Default::default();
+assert_eq!(items.claim(), ItemId(0));
+assert_eq!(format!("{:?}", items.claim()), "ItemId(1)");
+```
+
+Ids declared separately are distinct types, so they can't be mixed up:
+```compile_fail
+stable_id::define_id!(EntityId: u32);
+stable_id::define_id!(ItemId: u32);
+
+let mut entities: stable_id::Tec<EntityId, &str> = Default::default();
+entities.get(ItemId(0));
+```
+*/
+#[macro_export]
+macro_rules! define_id {
+    ($(#[$meta:meta])* $vis:vis $name:ident : $inner:ty) => {
+        $(#[$meta])*
+        #[derive($crate::StableId, Debug)]
+        $vis struct $name(pub $inner);
+    };
+}
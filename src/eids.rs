@@ -1,41 +1,262 @@
-use std::mem;
+pub(crate) mod freed_store;
 
-use stable_id_traits::{Maximum, Predecessor, Successor};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    iter, mem,
+    ops::{Deref, RangeInclusive},
+};
 
-use crate::Eids;
+use stable_id_traits::{CastUsize, Predecessor};
+
+use crate::{ClaimGuard, CoalesceStrategy, Eids, EidsIndex, IdAllocator, RecycleOrder, Sequence};
+
+use self::freed_store::FreedStore;
+
+/// `val.prev_value()`, as long as `val` is above `floor`, which proves that there's a value before `val`.
+/// Unlike `Predecessor::prev_value()`, this doesn't panic on the first value of `IndexT`.
+fn checked_prev<IndexT>(val: IndexT, floor: IndexT) -> Option<IndexT>
+where
+    IndexT: Predecessor + Ord,
+{
+    (floor < val).then(|| val.prev_value())
+}
 
 impl<IndexT> Eids<IndexT>
 where
-    IndexT: Successor + Predecessor + Clone + Copy + Ord + Maximum,
+    IndexT: EidsIndex,
 {
+    /**
+    Issue an id, preferring recycled ids over fresh ones.
+    Note: ids range from 0 to `IndexT::max_value() - 1`, unless the `full-range` feature is enabled,
+    in which case `IndexT::max_value()` can be claimed as well.
+    */
     pub fn claim(&mut self) -> IndexT {
+        #[cfg(not(feature = "full-range"))]
         assert!(
             self.next < IndexT::max_value(),
             "storing more items than you can address"
         );
 
-        self.freed
-            .iter()
-            .next()
-            .cloned()
-            .map(|id| {
-                // found an id in the free list, return it
-                let is_removed = self.freed.remove(&id);
-                debug_assert!(is_removed, "freeing something not in the database");
-                id
-            })
-            .unwrap_or_else(|| {
-                // otherwise increment the id and return it
-                let next = self.next.next_value();
-                mem::replace(&mut self.next, next)
-            })
+        let recycled = match self.recycle_order {
+            RecycleOrder::Smallest => self.freed.pop_first(),
+            RecycleOrder::MostRecent => self.pop_most_recent(),
+        };
+
+        // found an id in the free list, return it; otherwise increment the id and return it
+        recycled.unwrap_or_else(|| self.claim_unused())
+    }
+
+    /**
+    Same as [`Self::claim()`], but the id is unclaimed once the returned guard goes out of scope,
+    so it can't leak through an early return.
+
+    ```
+    use stable_id::Eids;
+
+    let mut entities: Eids<u8> = Default::default();
+    {
+        let id = entities.claim_scoped();
+        assert_eq!(*id, 0);
+    }
+    assert_eq!(entities.claim(), 0);
+    ```
+    */
+    pub fn claim_scoped(&mut self) -> ClaimGuard<'_, IndexT> {
+        let id = self.claim();
+        ClaimGuard { eids: self, id }
+    }
+
+    #[cfg(not(feature = "full-range"))]
+    fn claim_unused(&mut self) -> IndexT {
+        let next = self.next.next_value();
+        mem::replace(&mut self.next, next)
+    }
+
+    #[cfg(feature = "full-range")]
+    fn claim_unused(&mut self) -> IndexT {
+        assert!(!self.exhausted, "storing more items than you can address");
+
+        if self.next == IndexT::max_value() {
+            // can't increment any further, so just remember that the last id has been issued
+            self.exhausted = true;
+            self.next
+        } else {
+            let next = self.next.next_value();
+            mem::replace(&mut self.next, next)
+        }
+    }
+
+    /// Take back the largest issued id, i.e. the opposite of `claim_unused()`. `issued` is any issued id, which bounds
+    /// how far back `next` can go, so this returns `None` instead of stepping back past the first id.
+    #[cfg(not(feature = "full-range"))]
+    fn unclaim_last(&mut self, issued: IndexT) -> Option<IndexT> {
+        self.next = checked_prev(self.next, issued)?;
+        Some(self.next)
+    }
+
+    #[cfg(feature = "full-range")]
+    fn unclaim_last(&mut self, issued: IndexT) -> Option<IndexT> {
+        if self.exhausted {
+            if issued > self.next {
+                return None;
+            }
+            self.exhausted = false;
+        } else {
+            self.next = checked_prev(self.next, issued)?;
+        }
+        Some(self.next)
+    }
+
+    /// Whether `val` is the largest issued id. Only valid for issued ids.
+    #[cfg(not(feature = "full-range"))]
+    fn is_last_issued(&self, val: IndexT) -> bool {
+        // issued ids are below `next`, so there's room to increment
+        val.next_value() == self.next
+    }
+
+    #[cfg(feature = "full-range")]
+    fn is_last_issued(&self, val: IndexT) -> bool {
+        if self.exhausted {
+            val == self.next
+        } else {
+            val.next_value() == self.next
+        }
+    }
+
+    #[cfg(not(feature = "full-range"))]
+    fn is_issued(&self, val: IndexT) -> bool {
+        val < self.next
+    }
+
+    #[cfg(feature = "full-range")]
+    fn is_issued(&self, val: IndexT) -> bool {
+        val < self.next || (self.exhausted && val == self.next)
     }
 
     pub fn unclaim(&mut self, val: IndexT) {
-        assert!(val < self.next, "not a valid entity");
+        assert!(self.is_issued(val), "not a valid entity");
 
         let is_double_inserted = self.freed.insert(val);
-        debug_assert!(is_double_inserted, "double-freeing entity")
+        debug_assert!(is_double_inserted, "double-freeing entity");
+
+        if self.recycle_order == RecycleOrder::MostRecent {
+            self.recently_freed.push(val);
+        }
+    }
+
+    /**
+    Choose which freed id gets reused by [`Self::claim()`]. The default is [`RecycleOrder::Smallest`].
+
+    ```
+    use stable_id::{Eids, RecycleOrder};
+
+    let mut entities: Eids<u8> = Default::default();
+    (0..5).for_each(|_| {
+        entities.claim();
+    });
+
+    entities.set_recycle_order(RecycleOrder::MostRecent);
+    entities.unclaim(1);
+    entities.unclaim(3);
+
+    assert_eq!(entities.claim(), 3);
+    assert_eq!(entities.claim(), 1);
+    ```
+    */
+    pub fn set_recycle_order(&mut self, order: RecycleOrder) {
+        self.recycle_order = order;
+
+        if order == RecycleOrder::Smallest {
+            self.recently_freed = Vec::new();
+        }
+    }
+
+    pub fn recycle_order(&self) -> RecycleOrder {
+        self.recycle_order
+    }
+
+    fn pop_most_recent(&mut self) -> Option<IndexT> {
+        while let Some(id) = self.recently_freed.pop() {
+            if self.freed.remove(&id) {
+                return Some(id);
+            }
+        }
+
+        // ids that were freed before switching to RecycleOrder::MostRecent aren't tracked by the stack
+        self.freed.pop_first()
+    }
+
+    /**
+    The smallest id that has never been issued, i.e. what [`Self::claim()`] returns once the freed ids run out,
+    or `None` if there is no such id left.
+    */
+    #[cfg(not(feature = "full-range"))]
+    pub fn next_unused(&self) -> Option<IndexT> {
+        (self.next < IndexT::max_value()).then_some(self.next)
+    }
+
+    #[cfg(feature = "full-range")]
+    pub fn next_unused(&self) -> Option<IndexT> {
+        (!self.exhausted).then_some(self.next)
+    }
+
+    /**
+    The freed ids in ascending order, with consecutive ids merged into ranges.
+    Together with [`Self::next_unused()`], this is a compact representation of the generator's state,
+    which can be restored by [`Self::from_parts()`].
+
+    ```
+    use stable_id::Eids;
+
+    let mut entities: Eids<u8> = Default::default();
+    (0..10).for_each(|_| {
+        entities.claim();
+    });
+    [1, 2, 3, 5, 7, 8].into_iter().for_each(|id| entities.unclaim(id));
+
+    let ranges: Vec<_> = entities.freed_ranges().collect();
+    assert_eq!(ranges, [1..=3, 5..=5, 7..=8]);
+
+    let restored = Eids::from_parts(entities.next_unused(), ranges);
+    assert!(restored.freed_ranges().eq(entities.freed_ranges()));
+    assert_eq!(restored.next_unused(), Some(10));
+    ```
+    */
+    pub fn freed_ranges(&self) -> impl Iterator<Item = RangeInclusive<IndexT>> + '_ {
+        let mut ids = self.freed.iter().peekable();
+
+        iter::from_fn(move || {
+            let start = ids.next()?;
+            let mut end = start;
+
+            // ids are strictly increasing, so end is never the maximum value here
+            while let Some(id) = ids.next_if(|&id| end.next_value() == id) {
+                end = id;
+            }
+
+            Some(start..=end)
+        })
+    }
+
+    /**
+    Stop recycling ids: the returned [`Sequence`] carries on from the next unused id, and the freed ids are dropped.
+    See [`Sequence::into_eids()`] for the other way around.
+
+    ```
+    use stable_id::Eids;
+
+    let mut entities: Eids<u8> = Default::default();
+    let a = entities.claim();
+    entities.claim();
+    entities.unclaim(a);
+
+    let mut seq = entities.into_sequence();
+    assert_eq!(seq.next_value(), 2);
+    ```
+    */
+    pub fn into_sequence(self) -> Sequence<IndexT> {
+        Sequence::continue_from(self.next)
     }
 
     /**
@@ -80,9 +301,12 @@ where
             return;
         }
 
+        self.recently_freed.clear();
+
         while let Some(freed) = self.freed.pop_last() {
-            let target = self.next.prev_value();
-            self.next = target;
+            let target = self
+                .unclaim_last(freed)
+                .expect("freed ids are always issued");
 
             if target != freed {
                 f(target, freed);
@@ -91,9 +315,413 @@ where
     }
 }
 
+impl<IndexT> Eids<IndexT>
+where
+    IndexT: EidsIndex,
+{
+    /**
+    Same as [`Self::coalesce()`], but lets you choose which ids get relocated, see [`CoalesceStrategy`].
+
+    ```
+    use stable_id::{CoalesceStrategy, Eids};
+
+    let mut entities: Eids<u8> = Default::default();
+    (0..6).for_each(|_| {
+        entities.claim();
+    });
+    [1, 4, 5].into_iter().for_each(|id| entities.unclaim(id));
+
+    // only 4 & 5 can be taken back without moving anything
+    entities.coalesce_preferring(CoalesceStrategy::RelocateNone, |_, _| unreachable!());
+    assert_eq!(entities.next_unused(), Some(4));
+    assert_eq!(entities.claim(), 1);
+    ```
+    */
+    pub fn coalesce_preferring<F>(&mut self, strategy: CoalesceStrategy, f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        match strategy {
+            CoalesceStrategy::RelocateHighest => self.coalesce(f),
+            CoalesceStrategy::RelocateNone => {
+                while let Some(last) = self.freed.last() {
+                    if !self.is_last_issued(last) {
+                        break;
+                    }
+
+                    self.freed.pop_last();
+                    let unclaimed = self.unclaim_last(last);
+                    debug_assert!(unclaimed == Some(last));
+                }
+            }
+        }
+    }
+
+    /**
+    Same as [`Self::coalesce()`], but `f(old_id, new_id)` is called in ascending order of `old_id`,
+    whereas [`Self::coalesce()`] starts from the largest id. The relocations are buffered and sorted first,
+    which takes O(k lg k) extra time and O(k) extra memory for k relocations.
+    Since the calls are reordered, an id that [`Self::coalesce()`] would move twice is reported once, straight from
+    where it was to where it ends up, so that no id is both a target and a source of relocations.
+    This is handy when replaying the relocations on a sorted container, like a [`BTreeMap`](std::collections::BTreeMap).
+
+    ```
+    use stable_id::Eids;
+
+    let mut entities: Eids<u8> = Default::default();
+    (0..8).for_each(|_| {
+        entities.claim();
+    });
+    [1, 2, 6].into_iter().for_each(|id| entities.unclaim(id));
+
+    // coalesce() would report (7, 6), (6, 2), (5, 1)
+    let mut records = Vec::new();
+    entities.coalesce_sorted(|old_id, new_id| records.push((old_id, new_id)));
+    assert_eq!(records, [(5, 1), (7, 2)]);
+    ```
+    */
+    pub fn coalesce_sorted<F>(&mut self, mut f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        // current id -> id before coalescing
+        let mut origins = BTreeMap::new();
+        self.coalesce(|old_id, new_id| {
+            let origin = origins.remove(&old_id).unwrap_or(old_id);
+            origins.insert(new_id, origin);
+        });
+
+        let mut relocations: Vec<_> = origins
+            .into_iter()
+            .map(|(new_id, old_id)| (old_id, new_id))
+            .collect();
+        relocations.sort_unstable_by_key(|&(old_id, _)| old_id);
+        relocations
+            .into_iter()
+            .for_each(|(old_id, new_id)| f(old_id, new_id));
+    }
+}
+
+impl<IndexT> Eids<IndexT>
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    /**
+    Rebuild a generator that has issued every id below `next` (or every id, if `next` is `None`),
+    with the ids in `freed` unclaimed. This is the reverse of [`Self::next_unused()`] and [`Self::freed_ranges()`].
+
+    Panic if a freed id hasn't been issued, or if it's freed more than once.
+    */
+    pub fn from_parts<I>(next: Option<IndexT>, freed: I) -> Self
+    where
+        I: IntoIterator<Item = RangeInclusive<IndexT>>,
+    {
+        let mut result = Self {
+            freed: FreedStore::new(),
+            next: next.unwrap_or_else(IndexT::max_value),
+            recycle_order: Default::default(),
+            recently_freed: Vec::new(),
+            #[cfg(feature = "full-range")]
+            exhausted: next.is_none(),
+        };
+
+        freed.into_iter().for_each(|range| {
+            let (start, end) = range.into_inner();
+            if start > end {
+                return;
+            }
+
+            let mut id = start;
+            loop {
+                assert!(!result.freed.contains(&id), "double-freeing entity");
+                result.unclaim(id);
+
+                if id == end {
+                    break;
+                }
+                id = id.next_value();
+            }
+        });
+
+        result
+    }
+
+    /**
+    Import the state of another allocator that has issued every id below `next` and has `freed` them since,
+    without replaying its history. Same as [`Self::from_parts()`], but takes the freed ids one by one.
+
+    Panic if a freed id isn't below `next`, or if it's freed more than once.
+
+    ```
+    use stable_id::Eids;
+
+    let mut entities = Eids::<u8>::from_raw(5, [3, 1]);
+    assert_eq!(entities.claim(), 1);
+    assert_eq!(entities.claim(), 3);
+    assert_eq!(entities.claim(), 5);
+    ```
+    */
+    pub fn from_raw<I>(next: IndexT, freed: I) -> Self
+    where
+        I: IntoIterator<Item = IndexT>,
+    {
+        Self::from_parts(Some(next), freed.into_iter().map(|id| id..=id))
+    }
+
+    /**
+    Same as [`Self::coalesce()`], for the common case where the data lives in a `Vec` indexed by the ids.
+    The items are moved along with their ids, and then the vector is truncated so that it only holds the living items.
+
+    ```
+    use stable_id::Eids;
+
+    let mut entities: Eids<u8> = Default::default();
+    let mut data = vec![];
+    "abcde".chars().for_each(|c| {
+        entities.claim();
+        data.push(c);
+    });
+
+    entities.unclaim(1);
+    entities.unclaim(2);
+
+    entities.coalesce_vec(&mut data);
+    assert_eq!(data, ['a', 'd', 'e']);
+    ```
+    */
+    pub fn coalesce_vec<T>(&mut self, data: &mut Vec<T>) {
+        assert_eq!(
+            data.len(),
+            self.issued_len(),
+            "data doesn't match the issued ids"
+        );
+
+        self.coalesce(|old_id, new_id| data.swap(old_id.cast_to(), new_id.cast_to()));
+        data.truncate(self.issued_len());
+    }
+
+    /**
+    Take over the ids of `other`, so that both generators' live ids end up live in `self`.
+    `other`'s live ids are kept as they are unless they clash with a live id in `self`, in which case they're
+    moved to freshly claimed ids; deal with the change through `f(old_id, new_id)`.
+    Whatever is freed on both sides stays freed, and the unused ids pick up from the larger of the two.
+
+    ```
+    use stable_id::Eids;
+
+    let mut a: Eids<u8> = Default::default();
+    let mut b: Eids<u8> = Default::default();
+    (0..3).for_each(|_| {
+        a.claim();
+        b.claim();
+    });
+    b.claim();
+    a.unclaim(1);
+    b.unclaim(0);
+
+    let mut moved = Vec::new();
+    a.merge(b, |old_id, new_id| moved.push((old_id, new_id)));
+
+    // 1 and 3 fit into the space left by `a`, but 2 is taken
+    assert_eq!(moved, [(2, 4)]);
+    assert_eq!(a.next_unused(), Some(5));
+    assert_eq!(a.freed_ranges().count(), 0);
+    ```
+    */
+    pub fn merge<F>(&mut self, other: Self, mut f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        // keep as many ids in place as possible before handing out fresh ones to the leftovers
+        let clashing: Vec<_> = (0..other.issued_len())
+            .map(IndexT::cast_from)
+            .filter(|id| !other.freed.contains(id))
+            .filter(|&id| !self.claim_exact(id))
+            .collect();
+
+        clashing.into_iter().for_each(|old_id| {
+            let new_id = self.claim();
+            f(old_id, new_id);
+        });
+    }
+
+    /// Claim `id` itself, freeing the unused ids that are skipped over. Return false if `id` is live.
+    fn claim_exact(&mut self, id: IndexT) -> bool {
+        if self.is_issued(id) {
+            return self.freed.remove(&id);
+        }
+
+        loop {
+            let fresh = self.claim_unused();
+            if fresh == id {
+                return true;
+            }
+            self.unclaim(fresh);
+        }
+    }
+
+    /// The number of ids issued so far, including the freed ones.
+    #[cfg(not(feature = "full-range"))]
+    fn issued_len(&self) -> usize {
+        self.next.cast_to()
+    }
+
+    #[cfg(feature = "full-range")]
+    fn issued_len(&self) -> usize {
+        self.next.cast_to() + usize::from(self.exhausted)
+    }
+}
+
+/**
+Keeps track of the freed ids in a bitset if `IndexT` has at most 16 bits, which is cheaper to claim from and
+unclaim into than a B-tree, and in a B-tree otherwise. The same goes for every other constructor.
+*/
+impl<IndexT> Default for Eids<IndexT>
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    fn default() -> Self {
+        Self::from_parts(Some(IndexT::cast_from(0)), iter::empty())
+    }
+}
+
+/**
+Summarize the freed ids rather than dumping them, since there can be a lot of them after a burst of removals.
+A handful of freed ids are still listed one by one.
+
+```
+use stable_id::Eids;
+
+let mut entities: Eids<u16> = Default::default();
+(0..2000).for_each(|_| {
+    entities.claim();
+});
+(100..600).chain(1000..1500).for_each(|id| entities.unclaim(id));
+entities.unclaim(1800);
+
+assert_eq!(
+    format!("{entities:?}"),
+    "Eids { next: 2000, freed: 3 ranges covering 1001 ids, recycle_order: Smallest }"
+);
+```
+*/
+impl<IndexT> Debug for Eids<IndexT>
+where
+    IndexT: EidsIndex + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const LISTED_FREED: usize = 16;
+
+        let mut result = f.debug_struct("Eids");
+        result.field("next", &self.next);
+
+        // only worth mentioning once the maximum value itself has been issued
+        #[cfg(feature = "full-range")]
+        if self.exhausted {
+            result.field("exhausted", &self.exhausted);
+        }
+
+        if self.freed.len() < LISTED_FREED {
+            result.field("freed", &self.freed.iter().collect::<Vec<_>>());
+        } else {
+            let ranges = self.freed_ranges().count();
+            result.field(
+                "freed",
+                &format_args!("{} ranges covering {} ids", ranges, self.freed.len()),
+            );
+        }
+
+        result.field("recycle_order", &self.recycle_order).finish()
+    }
+}
+
+impl<IndexT> Deref for ClaimGuard<'_, IndexT>
+where
+    IndexT: EidsIndex,
+{
+    type Target = IndexT;
+
+    fn deref(&self) -> &Self::Target {
+        &self.id
+    }
+}
+
+impl<IndexT> Drop for ClaimGuard<'_, IndexT>
+where
+    IndexT: EidsIndex,
+{
+    fn drop(&mut self) {
+        self.eids.unclaim(self.id);
+    }
+}
+
+impl<IndexT> IdAllocator<IndexT> for Eids<IndexT>
+where
+    IndexT: EidsIndex,
+{
+    fn alloc(&mut self) -> IndexT {
+        self.claim()
+    }
+
+    fn free(&mut self, id: IndexT) {
+        self.unclaim(id)
+    }
+
+    fn peek_next(&self) -> IndexT {
+        let recycled = match self.recycle_order {
+            RecycleOrder::Smallest => None,
+            RecycleOrder::MostRecent => self
+                .recently_freed
+                .iter()
+                .rev()
+                .find(|id| self.freed.contains(id))
+                .copied(),
+        };
+
+        recycled.or_else(|| self.freed.first()).unwrap_or(self.next)
+    }
+}
+
 #[cfg(test)]
 mod eid_tests {
-    use super::Eids;
+    use std::collections::BTreeMap;
+
+    use stable_id_traits::{CastUsize, Maximum, Predecessor, Successor};
+
+    use super::{freed_store::FreedStore, Eids};
+    use crate::{CoalesceStrategy, EidsIndex, IdAllocator, RecycleOrder, Sequence};
+
+    /// A hand-written id type, to check that only the constructors need [`CastUsize`].
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+    struct Opaque(u8);
+
+    impl CastUsize for Opaque {
+        fn cast_from(val: usize) -> Self {
+            Self(val.try_into().expect("out of range"))
+        }
+
+        fn cast_to(self) -> usize {
+            self.0.into()
+        }
+    }
+
+    impl Successor for Opaque {
+        fn next_value(self) -> Self {
+            Self(self.0 + 1)
+        }
+    }
+
+    impl Predecessor for Opaque {
+        fn prev_value(self) -> Self {
+            Self(self.0 - 1)
+        }
+    }
+
+    impl Maximum for Opaque {
+        fn max_value() -> Self {
+            Self(u8::MAX)
+        }
+    }
 
     #[test]
     fn claim_ids() {
@@ -133,6 +761,7 @@ mod eid_tests {
     }
 
     #[test]
+    #[cfg(not(feature = "full-range"))]
     #[should_panic]
     fn claim_over_max() {
         let mut entities: Eids<u8> = Default::default();
@@ -140,4 +769,399 @@ mod eid_tests {
             entities.claim();
         });
     }
+
+    #[test]
+    fn recycle_most_recent() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..10).for_each(|_| {
+            entities.claim();
+        });
+
+        entities.unclaim(7);
+        entities.set_recycle_order(RecycleOrder::MostRecent);
+        entities.unclaim(2);
+        entities.unclaim(5);
+
+        assert_eq!(entities.claim(), 5);
+        assert_eq!(entities.claim(), 2);
+        assert_eq!(entities.claim(), 7); // freed before switching, fall back to the smallest
+        assert_eq!(entities.claim(), 10);
+
+        entities.unclaim(4);
+        entities.unclaim(1);
+        entities.set_recycle_order(RecycleOrder::Smallest);
+        entities.unclaim(8);
+
+        assert_eq!(entities.claim(), 1);
+        assert_eq!(entities.claim(), 4);
+        assert_eq!(entities.claim(), 8);
+    }
+
+    #[test]
+    fn id_allocator() {
+        let mut eids: Eids<u8> = Default::default();
+        let allocator: &mut dyn IdAllocator<u8> = &mut eids;
+
+        assert_eq!(allocator.alloc(), 0);
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 2);
+        assert_eq!(allocator.peek_next(), 3);
+
+        allocator.free(2);
+        allocator.free(0);
+        assert_eq!(allocator.peek_next(), 0);
+        assert_eq!(allocator.alloc(), 0);
+
+        eids.set_recycle_order(RecycleOrder::MostRecent);
+        eids.unclaim(1);
+        assert_eq!(eids.peek_next(), 1);
+        assert_eq!(eids.claim(), 1);
+        assert_eq!(eids.peek_next(), 2);
+    }
+
+    #[test]
+    fn coalesce_vec() {
+        let mut entities: Eids<u8> = Default::default();
+        let mut data: Vec<u8> = (0..100).map(|_| entities.claim()).collect();
+
+        (0..100u8)
+            .filter(|i| i % 7 == 0)
+            .for_each(|i| entities.unclaim(i));
+
+        entities.coalesce_vec(&mut data);
+
+        assert_eq!(data.len(), 85);
+        assert!(data.iter().all(|id| id % 7 != 0));
+
+        // the data vector can keep growing along with the ids
+        let id = entities.claim();
+        assert_eq!(id as usize, data.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "data doesn't match the issued ids")]
+    fn coalesce_vec_mismatch() {
+        let mut entities: Eids<u8> = Default::default();
+        entities.claim();
+        entities.coalesce_vec(&mut Vec::<u8>::new());
+    }
+
+    #[test]
+    #[cfg(feature = "full-range")]
+    fn claim_full_range() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..=u8::MAX).for_each(|i| {
+            assert_eq!(entities.claim(), i);
+        });
+
+        entities.unclaim(u8::MAX);
+        assert_eq!(entities.claim(), u8::MAX);
+
+        entities.unclaim(10);
+        let mut records = Vec::new();
+        entities.coalesce(|old_id, new_id| records.push((old_id, new_id)));
+        assert_eq!(records, [(u8::MAX, 10)]);
+
+        assert_eq!(entities.claim(), u8::MAX);
+    }
+
+    #[test]
+    #[cfg(feature = "full-range")]
+    #[should_panic(expected = "storing more items than you can address")]
+    fn claim_over_full_range() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..=256).for_each(|_| {
+            entities.claim();
+        });
+    }
+
+    #[test]
+    fn coalesce_all_freed() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..5).for_each(|_| {
+            entities.claim();
+        });
+
+        [3, 0, 4, 1, 2]
+            .into_iter()
+            .for_each(|i| entities.unclaim(i));
+
+        let mut records = Vec::new();
+        entities.coalesce(|old_id, new_id| records.push((old_id, new_id)));
+        assert!(records.is_empty());
+
+        // everything is reclaimed, so ids start over from 0
+        assert_eq!(entities.peek_next(), 0);
+        assert_eq!(entities.claim(), 0);
+        assert_eq!(entities.claim(), 1);
+    }
+
+    #[test]
+    fn without_cast_usize() {
+        // everything past the constructor only relies on the bounds of this function
+        fn claim_and_coalesce<IndexT: EidsIndex>(
+            entities: &mut Eids<IndexT>,
+        ) -> [(IndexT, IndexT); 2] {
+            let a = entities.claim();
+            let b = IdAllocator::alloc(entities);
+            let scoped = *entities.claim_scoped();
+
+            entities.unclaim(a);
+            let mut moves = Vec::new();
+            entities.coalesce(|old_id, new_id| moves.push((old_id, new_id)));
+            [(b, a), (scoped, entities.peek_next())]
+        }
+
+        let mut entities = Sequence::<Opaque>::default().into_eids();
+        let [(b, a), (scoped, next)] = claim_and_coalesce(&mut entities);
+        assert_eq!((b, a), (Opaque(1), Opaque(0)));
+        assert_eq!((scoped, next), (Opaque(2), Opaque(1)));
+    }
+
+    #[test]
+    fn constructors_pick_the_bitset() {
+        let is_dense = |entities: &Eids<u16>| matches!(entities.freed, FreedStore::Dense { .. });
+        assert!(is_dense(&Eids::default()));
+        assert!(is_dense(&Eids::from_raw(5, [1, 3])));
+        assert!(is_dense(&Eids::from_parts(None, [0..=2])));
+        assert!(is_dense(&Sequence::continue_from(7).into_eids()));
+        assert!(matches!(
+            Eids::<u32>::default().freed,
+            FreedStore::Sparse(_)
+        ));
+    }
+
+    #[test]
+    fn dense_matches_sparse() {
+        let mut dense = Eids::<u16>::default();
+        let mut sparse = Eids::<u32>::default();
+
+        for round in 0..5u16 {
+            let ids: Vec<_> = (0..100).map(|_| (dense.claim(), sparse.claim())).collect();
+            ids.iter().skip(round.into()).step_by(3).for_each(|(a, b)| {
+                dense.unclaim(*a);
+                sparse.unclaim(*b);
+            });
+            assert!(ids.iter().all(|(a, b)| u32::from(*a) == *b));
+        }
+        assert!(dense
+            .freed_ranges()
+            .map(|range| u32::from(*range.start())..=u32::from(*range.end()))
+            .eq(sparse.freed_ranges()));
+    }
+
+    #[test]
+    fn checked_prev() {
+        assert_eq!(super::checked_prev(0u8, 0), None);
+        assert_eq!(super::checked_prev(3u8, 3), None);
+        assert_eq!(super::checked_prev(3u8, 0), Some(2));
+        assert_eq!(super::checked_prev(u8::MAX, 0), Some(u8::MAX - 1));
+    }
+
+    #[test]
+    fn freed_ranges() {
+        let mut entities: Eids<u8> = Default::default();
+        assert_eq!(entities.freed_ranges().count(), 0);
+
+        (0..100).for_each(|_| {
+            entities.claim();
+        });
+        (0..100u8)
+            .filter(|i| !(10..20).contains(i) && i % 3 != 0)
+            .for_each(|i| entities.unclaim(i));
+
+        let ranges: Vec<_> = entities.freed_ranges().collect();
+        assert_eq!(ranges.first(), Some(&(1..=2)));
+        assert!(ranges.contains(&(20..=20)));
+        assert_eq!(ranges.last(), Some(&(97..=98)));
+
+        let mut restored = Eids::from_parts(entities.next_unused(), ranges);
+        assert!(restored.freed_ranges().eq(entities.freed_ranges()));
+
+        // both generators issue the same ids from now on
+        (0..100).for_each(|_| assert_eq!(restored.claim(), entities.claim()));
+    }
+
+    #[test]
+    fn from_parts_exhausted() {
+        let entities = Eids::<u8>::from_parts(None, [0..=0, u8::MAX - 1..=u8::MAX - 1]);
+        assert_eq!(entities.next_unused(), None);
+        assert!(entities
+            .freed_ranges()
+            .eq([0..=0, u8::MAX - 1..=u8::MAX - 1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "not a valid entity")]
+    fn from_parts_not_issued() {
+        Eids::<u8>::from_parts(Some(5), [3..=5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "double-freeing entity")]
+    fn from_parts_double_free() {
+        Eids::<u8>::from_parts(Some(5), [1..=3, 3..=4]);
+    }
+
+    #[test]
+    fn claim_scoped() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..5).for_each(|_| {
+            entities.claim();
+        });
+        entities.unclaim(2);
+
+        fn claim_and_bail(entities: &mut Eids<u8>) -> Result<u8, ()> {
+            let id = entities.claim_scoped();
+            assert_eq!(*id, 2);
+            Err(())
+        }
+        assert!(claim_and_bail(&mut entities).is_err());
+
+        // the id is reclaimed and handed back out
+        assert_eq!(entities.claim(), 2);
+        assert_eq!(entities.claim(), 5);
+    }
+
+    #[test]
+    fn coalesce_preferring() {
+        let freed = [2, 3, 7, 8, 9];
+        let setup = || {
+            let mut entities: Eids<u8> = Default::default();
+            (0..10).for_each(|_| {
+                entities.claim();
+            });
+            freed.iter().for_each(|id| entities.unclaim(*id));
+            entities
+        };
+
+        let mut highest = setup();
+        let mut records = Vec::new();
+        highest.coalesce_preferring(CoalesceStrategy::RelocateHighest, |old_id, new_id| {
+            records.push((old_id, new_id))
+        });
+        assert_eq!(records, [(6, 3), (5, 2)]);
+        assert_eq!(highest.next_unused(), Some(5));
+
+        let mut none = setup();
+        none.coalesce_preferring(CoalesceStrategy::RelocateNone, |_, _| unreachable!());
+        assert_eq!(none.next_unused(), Some(7));
+        assert!(none.freed_ranges().eq([2..=3]));
+
+        // everything freed
+        let mut entities: Eids<u8> = Default::default();
+        (0..3).for_each(|_| {
+            entities.claim();
+        });
+        (0..3).for_each(|id| entities.unclaim(id));
+        entities.coalesce_preferring(CoalesceStrategy::RelocateNone, |_, _| unreachable!());
+        assert_eq!(entities.next_unused(), Some(0));
+    }
+
+    #[test]
+    fn into_sequence_and_back() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..5).for_each(|_| {
+            entities.claim();
+        });
+        entities.unclaim(1);
+        entities.unclaim(3);
+
+        let mut seq = entities.into_sequence();
+        assert_eq!(seq.peek_value(), 5);
+        assert_eq!(seq.next_value(), 5);
+
+        let mut entities = seq.into_eids();
+        assert_eq!(entities.freed_ranges().count(), 0);
+        assert_eq!(entities.claim(), 6);
+        entities.unclaim(2);
+        assert_eq!(entities.claim(), 2);
+    }
+
+    #[test]
+    fn merge() {
+        let mut a: Eids<u8> = Default::default();
+        let mut b: Eids<u8> = Default::default();
+        (0..5).for_each(|_| {
+            a.claim();
+        });
+        (0..8).for_each(|_| {
+            b.claim();
+        });
+        [0, 3].into_iter().for_each(|id| a.unclaim(id));
+        [1, 3, 4, 6].into_iter().for_each(|id| b.unclaim(id));
+
+        let mut moved = Vec::new();
+        a.merge(b, |old_id, new_id| moved.push((old_id, new_id)));
+
+        // 0, 5 and 7 stay put, 2 clashes and takes over the freed 3
+        assert_eq!(moved, [(2, 3)]);
+        assert!(a.freed_ranges().eq([6..=6]));
+        assert_eq!(a.next_unused(), Some(8));
+    }
+
+    #[test]
+    fn debug_small_freed() {
+        let mut entities: Eids<u8> = Default::default();
+        (0..5).for_each(|_| {
+            entities.claim();
+        });
+        entities.unclaim(3);
+        entities.unclaim(1);
+
+        assert_eq!(
+            format!("{entities:?}"),
+            "Eids { next: 5, freed: [1, 3], recycle_order: Smallest }"
+        );
+    }
+
+    #[test]
+    fn from_raw() {
+        let entities = Eids::<u16>::from_raw(1000, (1..1000).step_by(2));
+        assert_eq!(entities.next_unused(), Some(1000));
+        assert_eq!(entities.freed_ranges().count(), 500);
+        assert!(entities
+            .freed_ranges()
+            .all(|range| range.start() == range.end()));
+
+        let entities = Eids::<u8>::from_raw(0, []);
+        assert_eq!(entities.next_unused(), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "not a valid entity")]
+    fn from_raw_not_issued() {
+        Eids::<u8>::from_raw(5, [1, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "double-freeing entity")]
+    fn from_raw_double_free() {
+        Eids::<u8>::from_raw(5, [1, 2, 1]);
+    }
+
+    #[test]
+    fn coalesce_sorted() {
+        let mut entities: Eids<u8> = Default::default();
+        let mut data = BTreeMap::new();
+        (0..200).for_each(|i| {
+            data.insert(entities.claim(), i);
+        });
+        (0..200).step_by(7).chain(150..170).for_each(|id| {
+            if data.remove(&id).is_some() {
+                entities.unclaim(id);
+            }
+        });
+
+        let mut records = Vec::new();
+        entities.coalesce_sorted(|old_id, new_id| {
+            records.push(old_id);
+            let value = data.remove(&old_id).unwrap();
+            assert!(data.insert(new_id, value).is_none());
+        });
+
+        assert!(records.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(data.keys().copied().eq(0..data.len() as u8));
+        assert_eq!(entities.freed_ranges().count(), 0);
+    }
 }
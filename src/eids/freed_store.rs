@@ -0,0 +1,308 @@
+/*!
+The set of freed ids of an [`crate::Eids`].
+
+Small id types (up to 16 bits) keep their freed ids in a bitset, which takes at most 8KiB for the whole id space
+and doesn't allocate nor rebalance in a hot claim/unclaim cycle. Bigger id types tend to be sparse, so they stay
+with a B-tree. The choice is made by [`FreedStore::new()`], which every constructor of [`crate::Eids`] goes through.
+The bitset has to turn ids into bit positions, so that's where `CastUsize` is needed; everything else works with any
+ordered id type.
+*/
+
+use std::collections::BTreeSet;
+
+use stable_id_traits::{CastUsize, Maximum};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Id types whose maximum value doesn't exceed this get a bitset.
+const DENSE_LIMIT: usize = u16::MAX as usize;
+
+#[derive(Clone)]
+pub(crate) enum FreedStore<IndexT> {
+    /// invariants:
+    ///     - the last word, if any, is non-zero, so the largest freed id is found in O(1)
+    ///     - every word before `lowest` is zero, so the search for the smallest freed id doesn't rescan them
+    Dense {
+        words: Vec<u64>,
+        len: usize,
+        lowest: usize,
+        positions: Positions<IndexT>,
+    },
+    Sparse(BTreeSet<IndexT>),
+}
+
+/// Turns ids into bit positions and back, captured by [`FreedStore::new()`] while `IndexT: CastUsize` is known.
+pub(crate) struct Positions<IndexT> {
+    to_position: fn(IndexT) -> usize,
+    from_position: fn(usize) -> IndexT,
+}
+
+impl<IndexT> Clone for Positions<IndexT> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<IndexT> Copy for Positions<IndexT> {}
+
+impl<IndexT> Positions<IndexT> {
+    fn split(&self, id: IndexT) -> (usize, u64) {
+        let position = (self.to_position)(id);
+        (position / WORD_BITS, 1 << (position % WORD_BITS))
+    }
+
+    fn join(&self, word_index: usize, bit: u32) -> IndexT {
+        (self.from_position)(word_index * WORD_BITS + bit as usize)
+    }
+}
+
+impl<IndexT> FreedStore<IndexT>
+where
+    IndexT: CastUsize + Maximum,
+{
+    /// Picks the bitset for id types of up to 16 bits, and the B-tree otherwise.
+    pub(crate) fn new() -> Self {
+        if IndexT::max_value().cast_to() <= DENSE_LIMIT {
+            Self::Dense {
+                words: Vec::new(),
+                len: 0,
+                lowest: 0,
+                positions: Positions {
+                    to_position: IndexT::cast_to,
+                    from_position: IndexT::cast_from,
+                },
+            }
+        } else {
+            Self::Sparse(BTreeSet::new())
+        }
+    }
+}
+
+impl<IndexT> FreedStore<IndexT>
+where
+    IndexT: Ord + Copy,
+{
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Dense { len, .. } => *len,
+            Self::Sparse(set) => set.len(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn contains(&self, id: &IndexT) -> bool {
+        match self {
+            Self::Dense {
+                words, positions, ..
+            } => {
+                let (word_index, mask) = positions.split(*id);
+                words.get(word_index).is_some_and(|word| word & mask != 0)
+            }
+            Self::Sparse(set) => set.contains(id),
+        }
+    }
+
+    /// Returns whether the id wasn't in the set.
+    pub(crate) fn insert(&mut self, id: IndexT) -> bool {
+        match self {
+            Self::Dense {
+                words,
+                len,
+                lowest,
+                positions,
+            } => {
+                let (word_index, mask) = positions.split(id);
+                if word_index >= words.len() {
+                    words.resize(word_index + 1, 0);
+                }
+
+                let is_new = words[word_index] & mask == 0;
+                words[word_index] |= mask;
+                *len += usize::from(is_new);
+                *lowest = (*lowest).min(word_index);
+                is_new
+            }
+            Self::Sparse(set) => set.insert(id),
+        }
+    }
+
+    /// Returns whether the id was in the set.
+    pub(crate) fn remove(&mut self, id: &IndexT) -> bool {
+        match self {
+            Self::Dense {
+                words,
+                len,
+                lowest,
+                positions,
+            } => {
+                let (word_index, mask) = positions.split(*id);
+                let Some(word) = words.get_mut(word_index) else {
+                    return false;
+                };
+
+                let was_present = *word & mask != 0;
+                *word &= !mask;
+                *len -= usize::from(was_present);
+
+                while words.last() == Some(&0) {
+                    words.pop();
+                }
+                *lowest = (*lowest).min(words.len());
+                was_present
+            }
+            Self::Sparse(set) => set.remove(id),
+        }
+    }
+
+    /// Where the smallest freed id is, skipping the words that are known to be zero.
+    fn first_word(words: &[u64], lowest: usize) -> Option<(usize, u64)> {
+        words
+            .iter()
+            .enumerate()
+            .skip(lowest)
+            .find(|(_, word)| **word != 0)
+            .map(|(word_index, word)| (word_index, *word))
+    }
+
+    pub(crate) fn first(&self) -> Option<IndexT> {
+        match self {
+            Self::Dense {
+                words,
+                lowest,
+                positions,
+                ..
+            } => Self::first_word(words, *lowest)
+                .map(|(word_index, word)| positions.join(word_index, word.trailing_zeros())),
+            Self::Sparse(set) => set.first().copied(),
+        }
+    }
+
+    pub(crate) fn last(&self) -> Option<IndexT> {
+        match self {
+            Self::Dense {
+                words, positions, ..
+            } => {
+                let word = words.last()?;
+                Some(positions.join(words.len() - 1, u64::BITS - 1 - word.leading_zeros()))
+            }
+            Self::Sparse(set) => set.last().copied(),
+        }
+    }
+
+    pub(crate) fn pop_first(&mut self) -> Option<IndexT> {
+        if let Self::Dense { words, lowest, .. } = self {
+            // remember the skipped words, so that a hot claim/unclaim cycle doesn't rescan them every time
+            *lowest =
+                Self::first_word(words, *lowest).map_or(words.len(), |(word_index, _)| word_index);
+        }
+
+        let first = self.first()?;
+        self.remove(&first);
+        Some(first)
+    }
+
+    pub(crate) fn pop_last(&mut self) -> Option<IndexT> {
+        let last = self.last()?;
+        self.remove(&last);
+        Some(last)
+    }
+
+    /// The freed ids in ascending order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = IndexT> + '_ {
+        let (dense, set) = match self {
+            Self::Dense {
+                words, positions, ..
+            } => (Some((words, positions)), None),
+            Self::Sparse(set) => (None, Some(set)),
+        };
+
+        let dense = dense.into_iter().flat_map(|(words, positions)| {
+            words
+                .iter()
+                .enumerate()
+                .flat_map(move |(word_index, &word)| {
+                    (0..u64::BITS)
+                        .filter(move |bit| word & (1 << bit) != 0)
+                        .map(move |bit| positions.join(word_index, bit))
+                })
+        });
+        let sparse = set.into_iter().flat_map(|set| set.iter().copied());
+
+        dense.chain(sparse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::FreedStore;
+
+    fn check<IndexT>(ids: &[IndexT])
+    where
+        IndexT:
+            stable_id_traits::CastUsize + stable_id_traits::Maximum + Ord + Copy + std::fmt::Debug,
+    {
+        let mut store = FreedStore::new();
+        let mut expected = BTreeSet::new();
+
+        for id in ids {
+            assert_eq!(store.insert(*id), expected.insert(*id));
+            assert_eq!(store.len(), expected.len());
+            assert_eq!(store.first(), expected.first().copied());
+            assert_eq!(store.last(), expected.last().copied());
+        }
+        assert!(store.iter().eq(expected.iter().copied()));
+        assert!(ids.iter().all(|id| store.contains(id)));
+
+        for (id, from_front) in ids.iter().zip([true, false].into_iter().cycle()) {
+            let popped = if from_front {
+                (store.pop_first(), expected.pop_first())
+            } else {
+                assert_eq!(store.remove(id), expected.remove(id));
+                (store.pop_last(), expected.pop_last())
+            };
+            assert_eq!(popped.0, popped.1);
+            assert_eq!(store.len(), expected.len());
+            assert!(store.iter().eq(expected.iter().copied()));
+        }
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn dense() {
+        assert!(matches!(FreedStore::<u16>::new(), FreedStore::Dense { .. }));
+        check(&[3u8, 255, 64, 63, 0, 3, 128, 1, 200]);
+        check(&[65535u16, 0, 4000, 4000, 64, 127, 128]);
+    }
+
+    #[test]
+    fn sparse() {
+        assert!(matches!(FreedStore::<u32>::new(), FreedStore::Sparse(_)));
+        check(&[3u32, u32::MAX, 64, 0, 3, 1 << 20]);
+    }
+
+    #[test]
+    fn lowest_word() {
+        let mut store = FreedStore::<u16>::new();
+        [5, 70, 200, 1000].into_iter().for_each(|id| {
+            store.insert(id);
+        });
+
+        assert_eq!(store.pop_first(), Some(5));
+        assert_eq!(store.pop_first(), Some(70));
+        assert!(matches!(store, FreedStore::Dense { lowest: 1, .. }));
+
+        // freeing a smaller id moves the cursor back
+        store.insert(3);
+        assert!(matches!(store, FreedStore::Dense { lowest: 0, .. }));
+        assert_eq!(store.pop_first(), Some(3));
+        assert_eq!(store.pop_first(), Some(200));
+        assert_eq!(store.pop_first(), Some(1000));
+        assert_eq!(store.pop_first(), None);
+        assert!(matches!(store, FreedStore::Dense { lowest: 0, .. }));
+    }
+}
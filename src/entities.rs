@@ -1,28 +1,84 @@
 use std::{
-    hash::Hash,
+    collections::BTreeMap,
+    error::Error,
+    fmt::Display,
+    hash::{BuildHasher, Hash},
+    mem,
     ops::{Index, IndexMut},
 };
 
-use rustc_hash::FxHashMap;
 use stable_id_traits::{CastUsize, Maximum, Successor};
 
-use crate::{Sequence, Tec};
+use crate::{
+    AccessError, ChangeEvent, EntitiesIndex, Handle, HashMap, ReinsertError, Sequence,
+    SparseEntities, StableCollection, Tec, TecIndex,
+};
 
 use super::Entities;
 
-impl<IndexT, DataT> Entities<IndexT, DataT>
+impl<IndexT> Handle<IndexT> {
+    /**
+    Turn a raw id back into a handle, e.g. when loading ids that were saved through [`Self::raw()`].
+    Nothing stops you from making up a handle that was never issued, but [`Entities`] treats it just like a removed one.
+    */
+    pub fn from_raw(raw: IndexT) -> Self {
+        Self(raw)
+    }
+
+    /** The underlying id, e.g. for serialization. */
+    pub fn raw(self) -> IndexT {
+        self.0
+    }
+}
+
+impl<IndexT, DataT, S> Entities<IndexT, DataT, S>
 where
-    IndexT: Default + Successor + Clone + Copy + Hash + Eq + CastUsize + Ord + Maximum,
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
 {
-    /** Reserves spaces similar to [`Vec::with_capacity()`]. */
-    pub fn with_capacity(capacity: usize) -> Self {
+    /**
+    Creates an empty collection whose virtual table hashes the ids with `hasher`, e.g. a fixed-seed hasher
+    for a reproducible iteration order.
+    */
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_capacity_and_hasher(0, hasher)
+    }
+
+    /** Combination of [`Self::with_capacity()`] and [`Self::with_hasher()`]. */
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
         Self {
-            vtable: Default::default(),
+            vtable: HashMap::with_capacity_and_hasher(capacity, hasher),
+            rvtable: Vec::with_capacity(capacity),
             data: Tec::with_capacity(capacity),
             seq: Default::default(),
+            changelog: None,
         }
     }
 
+    /**
+    Move every items out of a [`SparseEntities`] while keeping their ids, which become the raw values of the handles
+    (see [`Handle::from_raw()`]). The data is stored densely.
+    Newly allocated ids continue from where `sparse` left off, so they don't collide with ids issued by `sparse`.
+    */
+    pub fn from_sparse(sparse: SparseEntities<IndexT, DataT, S>) -> Self
+    where
+        S: Default,
+    {
+        let SparseEntities { data, seq } = sparse;
+
+        let mut result = Self {
+            seq,
+            ..Self::with_capacity_and_hasher(data.len(), Default::default())
+        };
+
+        data.into_iter().for_each(|(virtual_id, data)| {
+            let physical_id = result.data.alloc(data);
+            result.link(virtual_id, physical_id);
+        });
+
+        result
+    }
+
     /** Returns the number of items in this data structure. */
     pub fn len(&self) -> usize {
         self.data.len()
@@ -33,31 +89,150 @@ where
         self.data.is_empty()
     }
 
+    /** Number of items that have ever been allocated, see [`Tec::total_allocated()`]. */
+    pub fn total_allocated(&self) -> u64 {
+        self.data.total_allocated()
+    }
+
+    /**
+    Removes all items. The id sequence carries on, so ids issued afterward never collide with ids issued before.
+    */
+    pub fn clear(&mut self) {
+        if let Some(changelog) = &mut self.changelog {
+            let mut removed: Vec<_> = self.vtable.keys().copied().collect();
+            removed.sort_unstable();
+            changelog.extend(
+                removed
+                    .into_iter()
+                    .map(|id| ChangeEvent::Removed(Handle(id))),
+            );
+        }
+
+        self.vtable.clear();
+        self.rvtable.clear();
+        self.data.clear();
+    }
+
+    /**
+    Removes all items and restart the ids from zero.
+
+    **Warning**: unlike [`Self::clear()`], ids issued before will be issued again, so any handle that outlives this call
+    may silently refer to a new, unrelated item.
+    */
+    pub fn clear_and_reset(&mut self) {
+        self.clear();
+        self.seq = Default::default();
+    }
+
     /** Try getting the item with the given id. */
-    pub fn get(&self, index: IndexT) -> Option<&DataT> {
+    pub fn get(&self, index: Handle<IndexT>) -> Option<&DataT> {
         self.vtable
-            .get(&index)
+            .get(&index.raw())
             .and_then(|physical_id| self.data.get(*physical_id).map(|data| data))
     }
 
+    /**
+    Similar to [`Self::get()`], but tells you why the item can't be found.
+
+    ```
+    use stable_id::{AccessError, Entities, Handle};
+
+    let mut entities: Entities<u8, char> = Default::default();
+    let id = entities.alloc('a');
+    assert_eq!(entities.try_get(id), Ok(&'a'));
+
+    entities.remove(id);
+    assert_eq!(entities.try_get(id), Err(AccessError::Removed));
+    assert_eq!(entities.try_get(Handle::from_raw(123)), Err(AccessError::NeverIssued));
+    ```
+    */
+    pub fn try_get(&self, index: Handle<IndexT>) -> Result<&DataT, AccessError> {
+        self.get(index).ok_or_else(|| {
+            if self.is_issued(index) {
+                AccessError::Removed
+            } else {
+                AccessError::NeverIssued
+            }
+        })
+    }
+
+    /**
+    Whether `index` was issued by this collection but its item has been removed since, e.g. to tell a dangling reference
+    apart from a made-up one. Ids are never reissued (short of [`Self::clear_and_reset()`]), so a stale id stays stale.
+
+    ```
+    use stable_id::{Entities, Handle};
+
+    let mut entities: Entities<u8, char> = Default::default();
+    let id = entities.alloc('a');
+    assert!(!entities.is_stale(id));
+
+    entities.remove(id);
+    assert!(entities.is_stale(id));
+    assert!(!entities.is_stale(Handle::from_raw(123)));
+    ```
+    */
+    pub fn is_stale(&self, index: Handle<IndexT>) -> bool {
+        self.is_issued(index) && !self.vtable.contains_key(&index.raw())
+    }
+
+    /// Ids are issued in increasing order, so everything below the next id has been issued.
+    fn is_issued(&self, index: Handle<IndexT>) -> bool {
+        index.raw() < self.seq.peek_value()
+    }
+
     /** Mutable version of get. */
-    pub fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
+    pub fn get_mut(&mut self, index: Handle<IndexT>) -> Option<&mut DataT> {
         self.vtable
-            .get(&index)
+            .get(&index.raw())
             .and_then(|physical_id| self.data.get_mut(*physical_id).map(|data| data))
     }
 
+    /**
+    Translate an id issued by this collection into the physical id used by the backing [`Tec`].
+
+    Note: physical ids are not stable. They are reshuffled whenever the collection coalesces itself,
+    which may happen on any call to [`Self::remove()`].
+    */
+    pub fn physical_id(&self, virtual_id: Handle<IndexT>) -> Option<IndexT> {
+        self.vtable.get(&virtual_id.raw()).cloned()
+    }
+
+    /**
+    Get the item stored at the physical position `physical_id` of the backing [`Tec`], skipping the id table,
+    e.g. for a hot loop over physical ids cached through [`Self::physical_id()`].
+    Returns `None` if the position is out of bounds or holds no item.
+
+    Note: this is not the same as indexing with a [`Handle`]. Physical positions are only valid until the collection
+    coalesces itself, see [`Self::physical_id()`].
+
+    ```
+    use stable_id::Entities;
+
+    let mut entities: Entities<u8, char> = Default::default();
+    let a = entities.alloc('a');
+    let physical_id = entities.physical_id(a).unwrap();
+
+    assert_eq!(entities.physical(physical_id as usize), Some(&'a'));
+    assert_eq!(entities.physical(1), None);
+    ```
+    */
+    pub fn physical(&self, physical_id: usize) -> Option<&DataT> {
+        self.data.get_by_usize(physical_id)
+    }
+
     /**
     Removes an element for the given id.
     */
-    pub fn remove(&mut self, index: IndexT) -> Option<DataT> {
-        let virtual_id = index;
+    pub fn remove(&mut self, index: Handle<IndexT>) -> Option<DataT> {
+        let virtual_id = index.raw();
         let physical_id = self.vtable.get(&virtual_id);
 
         if let Some(&physical_id) = physical_id {
             let data = self.data.remove(physical_id);
 
             self.vtable.remove(&virtual_id).expect("cannot remove item"); // contradiction: we just found the physical id
+            self.log_change(ChangeEvent::Removed(index));
 
             assert_eq!(self.vtable.len(), self.data.len());
 
@@ -84,13 +259,152 @@ where
     /**
     Allocate an entity with monotonically increase ids, just like [`crate::SparseEntities`].
     */
-    pub fn alloc(&mut self, data: DataT) -> IndexT {
+    pub fn alloc(&mut self, data: DataT) -> Handle<IndexT> {
         let virtual_id = self.seq.next_value();
         let phyiscal_id = self.data.alloc(data);
 
-        self.vtable.insert(virtual_id, phyiscal_id);
+        self.link(virtual_id, phyiscal_id);
+        self.log_change(ChangeEvent::Added(Handle(virtual_id)));
 
-        virtual_id
+        Handle(virtual_id)
+    }
+
+    /**
+    Same as [`Self::alloc()`], but the backing storage is compacted first if the dead slots outnumber
+    `max_dead_ratio` times the living items, so that items allocated in a row end up next to each other after the
+    living ones rather than scattered over the holes. A ratio of `0.0` compacts whenever there is any dead slot.
+
+    Note: [`Self::remove()`] already keeps the number of dead slots below log2 of the length, so this only ever
+    fills a few holes. The compaction costs a pass over the id table, which the threshold keeps from happening
+    on every call when the holes are too few to matter.
+
+    ```
+    use stable_id::Entities;
+
+    let mut entities: Entities<u8, u32> = Default::default();
+    let ids: Vec<_> = (0..20).map(|i| entities.alloc(i)).collect();
+    entities.remove(ids[3]);
+
+    let a = entities.alloc_compacting(100, 0.0);
+    let b = entities.alloc_compacting(101, 0.0);
+    assert_eq!(entities.physical_id(a), Some(19));
+    assert_eq!(entities.physical_id(b), Some(20));
+    assert_eq!(entities.physical_id(ids[19]), Some(3));
+    ```
+    */
+    pub fn alloc_compacting(&mut self, data: DataT, max_dead_ratio: f64) -> Handle<IndexT> {
+        let dead_count = self.data.capacity() - self.len();
+        if dead_count > 0 && dead_count as f64 > max_dead_ratio * self.len() as f64 {
+            self.coalesce();
+        }
+
+        self.alloc(data)
+    }
+
+    /**
+    Similar to [`Self::alloc()`], but stores `data` in the physical slot that is closest to the one of `hint`,
+    which keeps related items close to each other in memory, e.g. for neighbourhood queries.
+    The candidates are the dead slots and the end of the storage. Falls back to [`Self::alloc()`] if `hint` isn't alive.
+    Takes linear time in the number of dead slots.
+
+    ```
+    use stable_id::Entities;
+
+    let mut entities: Entities<u8, u32> = Default::default();
+    let ids: Vec<_> = (0..20).map(|i| entities.alloc(i)).collect();
+    entities.remove(ids[4]);
+    entities.remove(ids[12]); // alloc() would reuse this slot
+
+    let near = entities.alloc_near(100, ids[5]);
+    assert_eq!(entities.physical_id(near), Some(4));
+    assert_eq!(entities.physical_id(ids[5]), Some(5));
+    ```
+    */
+    pub fn alloc_near(&mut self, data: DataT, hint: Handle<IndexT>) -> Handle<IndexT> {
+        let Some(hint) = self.physical_id(hint) else {
+            return self.alloc(data);
+        };
+
+        let hint = hint.cast_to();
+        let end = self.data.capacity();
+        let nearest = self
+            .data
+            .free_list()
+            .map(CastUsize::cast_to)
+            .min_by_key(|position: &usize| position.abs_diff(hint))
+            .filter(|position| position.abs_diff(hint) < end.abs_diff(hint));
+
+        let phyiscal_id = match nearest {
+            Some(position) => {
+                let phyiscal_id = IndexT::cast_from(position);
+                self.data
+                    .alloc_at_dead(phyiscal_id, data)
+                    .expect("free list contains a living slot");
+                phyiscal_id
+            }
+            None => self.data.alloc_at_end(data),
+        };
+
+        let virtual_id = self.seq.next_value();
+        self.link(virtual_id, phyiscal_id);
+        self.log_change(ChangeEvent::Added(Handle(virtual_id)));
+
+        Handle(virtual_id)
+    }
+
+    /**
+    Put `data` back under a removed id, e.g. to undo a removal so that the old handles keep working.
+    The item gets a new physical slot, and the id sequence is left untouched.
+
+    ```
+    use stable_id::{Entities, Handle, ReinsertError};
+
+    let mut entities: Entities<u8, char> = Default::default();
+    let id = entities.alloc('a');
+
+    let removed = entities.remove(id).unwrap();
+    assert_eq!(entities.reinsert(id, removed), Ok(()));
+    assert_eq!(entities[id], 'a');
+
+    assert_eq!(entities.reinsert(id, 'b'), Err(ReinsertError::Occupied));
+    assert_eq!(entities.reinsert(Handle::from_raw(1), 'b'), Err(ReinsertError::NeverIssued));
+    ```
+    */
+    pub fn reinsert(
+        &mut self,
+        virtual_id: Handle<IndexT>,
+        data: DataT,
+    ) -> Result<(), ReinsertError> {
+        if !self.is_issued(virtual_id) {
+            return Err(ReinsertError::NeverIssued);
+        }
+
+        if self.vtable.contains_key(&virtual_id.raw()) {
+            return Err(ReinsertError::Occupied);
+        }
+
+        let phyiscal_id = self.data.alloc(data);
+        self.link(virtual_id.raw(), phyiscal_id);
+        self.log_change(ChangeEvent::Added(virtual_id));
+
+        Ok(())
+    }
+
+    /**
+    Same as [`Self::alloc()`], but the item is always stored after the last physical slot (see [`Tec::alloc_at_end()`]),
+    so that the physical layout follows the allocation order.
+
+    Note: [`Self::remove()`] coalesces the backing storage once there are enough dead slots, which moves items from the
+    back into the holes, so the physical order only stays in allocation order as long as no item is removed.
+    */
+    pub fn alloc_at_end(&mut self, data: DataT) -> Handle<IndexT> {
+        let virtual_id = self.seq.next_value();
+        let physical_id = self.data.alloc_at_end(data);
+
+        self.link(virtual_id, physical_id);
+        self.log_change(ChangeEvent::Added(Handle(virtual_id)));
+
+        Handle(virtual_id)
     }
 
     /// Return all data's references.
@@ -106,30 +420,267 @@ where
     /**
     Iterate every entries. This takes O(`HashMap::iter()`) to iterate the entire collection.
     */
-    pub fn iter_with_id(&self) -> impl Iterator<Item = (IndexT, &DataT)> {
+    pub fn iter_with_id(&self) -> impl Iterator<Item = (Handle<IndexT>, &DataT)> {
         self.vtable.iter().map(|(virtual_id, physical_id)| {
             let data = &self.data[*physical_id];
 
-            (*virtual_id, data)
+            (Handle(*virtual_id), data)
         })
     }
 
+    /**
+    Iterate every entries in the order they are laid out in the backing [`Tec`], which is friendlier to the cache than
+    [`Self::iter_with_id()`]. Each virtual id is looked up as its item is visited, so nothing is allocated up front.
+    */
+    pub fn iter_with_id_physical(&self) -> impl Iterator<Item = (Handle<IndexT>, &DataT)> {
+        self.data
+            .iter_positions()
+            .map(|(position, _, data)| (Handle(self.rvtable[position]), data))
+    }
+
+    /** Count the items that satisfy `f`, see [`Tec::count_by()`]. */
+    pub fn count_by<F>(&self, f: F) -> usize
+    where
+        F: FnMut(&DataT) -> bool,
+    {
+        self.data.count_by(f)
+    }
+
+    /**
+    Call `f` on every entries in physical order, see [`Self::iter_with_id_physical()`].
+    Handy for hot loops that only need the virtual ids once in a while.
+
+    ```
+    use stable_id::Entities;
+
+    let mut entities: Entities<u8, u32> = Default::default();
+    let a = entities.alloc(1);
+    entities.alloc(2);
+
+    let mut total = 0;
+    let mut seen_a = false;
+    entities.for_each_physical(|id, data| {
+        total += data;
+        seen_a |= id == a;
+    });
+    assert_eq!((total, seen_a), (3, true));
+    ```
+    */
+    pub fn for_each_physical<'a, F>(&'a self, mut f: F)
+    where
+        F: FnMut(Handle<IndexT>, &'a DataT),
+    {
+        self.data
+            .iter_positions()
+            .for_each(|(position, _, data)| f(Handle(self.rvtable[position]), data));
+    }
+
+    /**
+    Split the items into 2 aligned vectors, i.e. the `i`-th id refers to the `i`-th item, which is handy for
+    uploading the data somewhere else as a structure of arrays. The items are in the same order as
+    [`Self::iter_with_id_physical()`].
+    */
+    pub fn to_parallel_vecs(&self) -> (Vec<Handle<IndexT>>, Vec<&DataT>) {
+        self.iter_with_id_physical().unzip()
+    }
+
+    /**
+    Consuming version of [`Self::to_parallel_vecs()`].
+    */
+    pub fn into_parallel_vecs(self) -> (Vec<Handle<IndexT>>, Vec<DataT>) {
+        self.into_iter_with_id().unzip()
+    }
+
+    /**
+    Move every entries out in the same order as [`Self::iter_with_id_physical()`].
+    */
+    pub fn into_iter_with_id(self) -> impl Iterator<Item = (Handle<IndexT>, DataT)> {
+        let rvtable = self.rvtable;
+
+        self.data
+            .into_iter_with_id()
+            .map(move |(physical_id, data)| (Handle(rvtable[physical_id.cast_to()]), data))
+    }
+
+    /**
+    Iterate every entries in ascending order of their ids. Unlike [`Self::iter_with_id()`], the order is deterministic,
+    at the cost of collecting and sorting all ids up front, i.e. O(n lg n).
+    */
+    pub fn iter_sorted(&self) -> impl Iterator<Item = (Handle<IndexT>, &DataT)> {
+        let mut virtual_ids: Vec<_> = self.vtable.keys().cloned().map(Handle).collect();
+        virtual_ids.sort_unstable();
+
+        virtual_ids
+            .into_iter()
+            .map(|virtual_id| (virtual_id, &self[virtual_id]))
+    }
+
+    /**
+    Clone every entry into a [`Vec`] sorted by the ids (see [`Self::iter_sorted()`]),
+    so that two snapshots of the same content compare and hash the same, e.g. for checkpoints.
+
+    ```
+    use stable_id::Entities;
+
+    let mut entities: Entities<u8, char> = Default::default();
+    let a = entities.alloc('a');
+    let b = entities.alloc('b');
+    let c = entities.alloc('c');
+    entities.remove(b);
+
+    assert_eq!(entities.to_vec(), [(a, 'a'), (c, 'c')]);
+    ```
+    */
+    pub fn to_vec(&self) -> Vec<(Handle<IndexT>, DataT)>
+    where
+        DataT: Clone,
+    {
+        self.iter_sorted()
+            .map(|(virtual_id, data)| (virtual_id, data.clone()))
+            .collect()
+    }
+
+    /**
+    Collect references of all items into a [`BTreeMap`], which is sorted by the ids.
+    */
+    pub fn to_btreemap(&self) -> BTreeMap<Handle<IndexT>, &DataT> {
+        self.iter_with_id().collect()
+    }
+
+    /**
+    Consuming version of [`Self::to_btreemap()`].
+    */
+    pub fn into_btreemap(self) -> BTreeMap<Handle<IndexT>, DataT> {
+        self.into_iter_with_id().collect()
+    }
+
+    /**
+    Compact the backing storage and then release the memory that is no longer needed,
+    including the spare capacity of the hash table that maps the ids, which never shrinks on its own.
+    */
+    pub fn shrink_to_fit(&mut self) {
+        self.coalesce();
+        self.data.shrink_to_fit();
+        self.vtable.shrink_to_fit();
+        self.rvtable.truncate(self.data.capacity());
+        self.rvtable.shrink_to_fit();
+    }
+
+    /**
+    Compact the backing storage incrementally by relocating at most `max_moves` items, see [`Tec::coalesce_budgeted()`].
+    Returns whether the storage is fully compact. Handles stay valid all along.
+
+    Only the moved items get their ids updated, so the cost is bounded by `max_moves` rather than the length.
+    Note: [`Self::remove()`] may coalesce all at once regardless.
+    */
+    pub fn coalesce_budgeted(&mut self, max_moves: usize) -> bool {
+        let Self {
+            vtable,
+            rvtable,
+            data,
+            ..
+        } = self;
+
+        let is_compact = data.coalesce_budgeted(max_moves, |old_physical_id, new_physical_id| {
+            Self::relink(vtable, rvtable, old_physical_id, new_physical_id);
+        });
+        self.rvtable.truncate(self.data.capacity());
+
+        is_compact
+    }
+
+    /**
+    Start recording the items that get added or removed, e.g. to replicate the changes without diffing snapshots.
+    Compacting the storage only moves items around, so it isn't recorded. Does nothing if already enabled.
+
+    ```
+    use stable_id::{ChangeEvent, Entities};
+
+    let mut entities: Entities<u8, char> = Default::default();
+    let a = entities.alloc('a');
+    entities.enable_changelog();
+
+    let b = entities.alloc('b');
+    entities.remove(a);
+    assert_eq!(
+        entities.drain_changelog(),
+        [ChangeEvent::Added(b), ChangeEvent::Removed(a)]
+    );
+    assert!(entities.drain_changelog().is_empty());
+    ```
+    */
+    pub fn enable_changelog(&mut self) {
+        self.changelog.get_or_insert_with(Vec::new);
+    }
+
+    /** Stop recording changes and drop the ones that haven't been drained. */
+    pub fn disable_changelog(&mut self) {
+        self.changelog = None;
+    }
+
+    /**
+    Take the changes recorded since the last call, oldest first. Always empty unless [`Self::enable_changelog()`] is called.
+    */
+    pub fn drain_changelog(&mut self) -> Vec<ChangeEvent<IndexT>> {
+        self.changelog.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    fn log_change(&mut self, event: ChangeEvent<IndexT>) {
+        if let Some(changelog) = &mut self.changelog {
+            changelog.push(event);
+        }
+    }
+
     /**
     Compact spaces internally.
     */
     fn coalesce(&mut self) {
-        let reverse_mapping: FxHashMap<_, _> = self.vtable.iter().map(|(a, b)| (*b, *a)).collect();
+        let Self {
+            vtable,
+            rvtable,
+            data,
+            ..
+        } = self;
+
+        data.coalesce(|old_physical_id, new_physical_id| {
+            Self::relink(vtable, rvtable, old_physical_id, new_physical_id);
+        });
+        self.rvtable.truncate(self.data.capacity());
+    }
 
-        self.data.coalesce(|old_physical_id, new_physical_id| {
-            let virtual_id = reverse_mapping
-                .get(&old_physical_id)
-                .cloned()
-                .expect("inconsistent index");
+    /// Record that the item at `physical_id` is known as `virtual_id`.
+    fn link(&mut self, virtual_id: IndexT, physical_id: IndexT) {
+        self.vtable.insert(virtual_id, physical_id);
 
-            self.vtable.entry(virtual_id).and_modify(|c| {
-                *c = new_physical_id;
-            });
-        })
+        // the backing storage only ever grows by one slot at a time
+        let position = physical_id.cast_to();
+        if position == self.rvtable.len() {
+            self.rvtable.push(virtual_id);
+        } else {
+            self.rvtable[position] = virtual_id;
+        }
+    }
+
+    /// Follow an item that the backing storage moved from `old_physical_id` to `new_physical_id`.
+    fn relink(
+        vtable: &mut HashMap<IndexT, IndexT, S>,
+        rvtable: &mut [IndexT],
+        old_physical_id: IndexT,
+        new_physical_id: IndexT,
+    ) {
+        let virtual_id = rvtable[old_physical_id.cast_to()];
+        rvtable[new_physical_id.cast_to()] = virtual_id;
+        *vtable.get_mut(&virtual_id).expect("inconsistent index") = new_physical_id;
+    }
+}
+
+impl<IndexT, DataT> Entities<IndexT, DataT>
+where
+    IndexT: EntitiesIndex,
+{
+    /** Reserves spaces similar to [`Vec::with_capacity()`]. */
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, Default::default())
     }
 }
 
@@ -140,15 +691,18 @@ where
     fn default() -> Self {
         Self {
             vtable: Default::default(),
+            rvtable: Default::default(),
             data: Default::default(),
             seq: Default::default(),
+            changelog: None,
         }
     }
 }
 
-impl<IndexT, DataT> Entities<IndexT, DataT>
+impl<IndexT, DataT, S> Entities<IndexT, DataT, S>
 where
-    IndexT: Successor + CastUsize + Ord + Copy + Maximum + Hash,
+    IndexT: TecIndex + Successor + Hash,
+    S: BuildHasher + Default,
     DataT: Clone,
 {
     /**
@@ -165,13 +719,20 @@ where
             })
             .collect();
 
-        Self { vtable, data, seq }
+        Self {
+            rvtable: (0..count).map(CastUsize::cast_from).collect(),
+            vtable,
+            data,
+            seq,
+            changelog: None,
+        }
     }
 }
 
-impl<IndexT, DataT> Entities<IndexT, DataT>
+impl<IndexT, DataT, S> Entities<IndexT, DataT, S>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum + Successor + Hash,
+    IndexT: TecIndex + Successor + Hash,
+    S: BuildHasher + Default,
     DataT: Clone + Default,
 {
     /**
@@ -182,37 +743,90 @@ where
     }
 }
 
-impl<IndexT, DataT> Index<IndexT> for Entities<IndexT, DataT>
+impl<IndexT, DataT, S> StableCollection<Handle<IndexT>, DataT> for Entities<IndexT, DataT, S>
+where
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
+{
+    fn alloc(&mut self, data: DataT) -> Handle<IndexT> {
+        Entities::alloc(self, data)
+    }
+
+    fn get(&self, index: Handle<IndexT>) -> Option<&DataT> {
+        Entities::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: Handle<IndexT>) -> Option<&mut DataT> {
+        Entities::get_mut(self, index)
+    }
+
+    fn remove(&mut self, index: Handle<IndexT>) -> Option<DataT> {
+        Entities::remove(self, index)
+    }
+
+    fn len(&self) -> usize {
+        Entities::len(self)
+    }
+
+    fn iter_with_id<'a>(&'a self) -> impl Iterator<Item = (Handle<IndexT>, &'a DataT)>
+    where
+        DataT: 'a,
+    {
+        Entities::iter_with_id(self)
+    }
+}
+
+impl<IndexT, DataT, S> Index<Handle<IndexT>> for Entities<IndexT, DataT, S>
 where
-    IndexT: Successor + Clone + Copy + Hash + Eq + Default + CastUsize + Ord + Maximum,
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
 {
     type Output = DataT;
 
-    fn index(&self, index: IndexT) -> &Self::Output {
+    fn index(&self, index: Handle<IndexT>) -> &Self::Output {
         self.get(index).expect("element not exist")
     }
 }
 
-impl<IndexT, DataT> IndexMut<IndexT> for Entities<IndexT, DataT>
+impl<IndexT, DataT, S> IndexMut<Handle<IndexT>> for Entities<IndexT, DataT, S>
 where
-    IndexT: Successor + Clone + Copy + Hash + Eq + Default + CastUsize + Ord + Maximum,
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
 {
-    fn index_mut(&mut self, index: IndexT) -> &mut Self::Output {
+    fn index_mut(&mut self, index: Handle<IndexT>) -> &mut Self::Output {
         self.get_mut(index).expect("element not exist")
     }
 }
 
+impl Display for AccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessError::NeverIssued => write!(f, "id has never been issued"),
+            AccessError::Removed => write!(f, "id refers to a removed item"),
+        }
+    }
+}
+
+impl Error for AccessError {}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::{HashMap, HashSet};
+    use std::{
+        collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+        hash::BuildHasherDefault,
+    };
 
-    use crate::Entities;
+    use crate::{ChangeEvent, Entities, Handle, ReinsertError, SparseEntities};
+
+    fn h<IndexT>(raw: IndexT) -> Handle<IndexT> {
+        Handle::from_raw(raw)
+    }
 
     #[test]
     fn access_out_of_bound() {
         let mut entities = Entities::default();
         entities.alloc(1232);
-        assert_eq!(entities.get(312u16), None);
+        assert_eq!(entities.get(h(312u16)), None);
     }
 
     #[test]
@@ -220,7 +834,7 @@ mod tests {
     fn access_out_of_bound_mut() {
         let mut entities = Entities::default();
         entities.alloc(1232);
-        entities[312u16] = 3333;
+        entities[h(312u16)] = 3333;
     }
 
     #[test]
@@ -229,7 +843,7 @@ mod tests {
         let mut entities = Entities::<u8, usize>::populate_defaults(count);
 
         assert_eq!(entities.len(), count);
-        assert_eq!(entities.alloc(54354534), count as u8);
+        assert_eq!(entities.alloc(54354534), h(count as u8));
         assert_eq!(entities.len(), count + 1);
     }
 
@@ -252,23 +866,23 @@ mod tests {
             .into_iter()
             .for_each(|(id, data)| assert_eq!(entities[id], data));
 
-        assert_eq!(entities.remove(1), Some("1"));
+        assert_eq!(entities.remove(h(1)), Some("1"));
         check_all(&entities);
 
-        assert_eq!(entities.remove(4), Some("4"));
+        assert_eq!(entities.remove(h(4)), Some("4"));
         check_all(&entities);
 
-        assert_eq!(entities.remove(5), Some("5"));
+        assert_eq!(entities.remove(h(5)), Some("5"));
         check_all(&entities);
 
-        assert_eq!(entities.remove(3), Some("3"));
+        assert_eq!(entities.remove(h(3)), Some("3"));
         check_all(&entities);
 
-        assert_eq!(entities.remove(2), Some("2"));
+        assert_eq!(entities.remove(h(2)), Some("2"));
         assert_eq!(entities.len(), 1);
         check_all(&entities);
 
-        assert_eq!(entities.remove(0), Some("0"));
+        assert_eq!(entities.remove(h(0)), Some("0"));
         assert!(entities.is_empty());
         check_all(&entities);
     }
@@ -299,19 +913,19 @@ mod tests {
         .into_iter()
         .for_each(|(id, data)| assert_eq!(entities[id], data));
 
-        assert_eq!(entities.remove(1), Some("1".to_owned()));
+        assert_eq!(entities.remove(h(1)), Some("1".to_owned()));
         check_all(&entities);
 
-        assert_eq!(entities.remove(4), Some("4".to_owned()));
+        assert_eq!(entities.remove(h(4)), Some("4".to_owned()));
         check_all(&entities);
 
-        assert_eq!(entities.remove(5), Some("5".to_owned()));
+        assert_eq!(entities.remove(h(5)), Some("5".to_owned()));
         check_all(&entities);
 
-        assert_eq!(entities.remove(2), Some("2".to_owned()));
+        assert_eq!(entities.remove(h(2)), Some("2".to_owned()));
         check_all(&entities);
 
-        let data_with_id = HashSet::from([(3, "3".to_owned()), (0, "0".to_owned())]);
+        let data_with_id = HashSet::from([(h(3), "3".to_owned()), (h(0), "0".to_owned())]);
 
         assert_eq!(
             HashSet::from(["3".to_owned(), "0".to_owned()]),
@@ -331,7 +945,7 @@ mod tests {
             .for_each(|value| *value = format!("1{value}"));
 
         assert_eq!(
-            HashSet::from([(3, "13".to_owned()), (0, "10".to_owned())]),
+            HashSet::from([(h(3), "13".to_owned()), (h(0), "10".to_owned())]),
             entities
                 .iter_with_id()
                 .map(|(id, value)| (id, value.to_owned()))
@@ -339,19 +953,272 @@ mod tests {
         );
     }
 
+    #[test]
+    fn iter_sorted() {
+        let mut entities: Entities<u8, char> = Default::default();
+
+        ['a', 'b', 'c', 'd', 'e', 'f'].into_iter().for_each(|c| {
+            entities.alloc(c);
+        });
+
+        entities.remove(h(1));
+        entities.remove(h(4));
+
+        assert_eq!(
+            vec![(h(0), &'a'), (h(2), &'c'), (h(3), &'d'), (h(5), &'f')],
+            entities.iter_sorted().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn physical_id() {
+        let mut entities: Entities<usize, char> = Default::default();
+
+        ['a', 'b', 'c', 'd', 'e'].into_iter().for_each(|c| {
+            entities.alloc(c);
+        });
+
+        assert_eq!(entities.physical_id(h(4)), Some(4));
+
+        entities.remove(h(2));
+        entities.remove(h(3));
+        entities.remove(h(1));
+
+        // 'e' got relocated when the collection coalesced itself
+        let physical_id = entities.physical_id(h(4)).expect("item should be alive");
+        assert_eq!(entities.data[physical_id], 'e');
+        assert!(physical_id < entities.len());
+
+        assert_eq!(entities.physical_id(h(1)), None);
+        assert_eq!(entities.physical_id(h(100)), None);
+    }
+
+    #[test]
+    fn total_allocated() {
+        let mut entities = Entities::<u8, usize>::populate_defaults(5);
+        assert_eq!(entities.total_allocated(), 5);
+
+        entities.alloc(5);
+        entities.remove(h(1));
+        entities.remove(h(2));
+        entities.remove(h(3)); // triggers coalesce
+        entities.alloc(6);
+
+        assert_eq!(entities.len(), 4);
+        assert_eq!(entities.total_allocated(), 7);
+    }
+
+    #[test]
+    fn to_btreemap() {
+        let mut entities: Entities<u8, String> = Default::default();
+
+        ["a", "b", "c", "d", "e"].into_iter().for_each(|c| {
+            entities.alloc(c.to_owned());
+        });
+
+        entities.remove(h(0));
+        entities.remove(h(2));
+        entities.remove(h(1)); // triggers coalesce
+
+        let expected = BTreeMap::from([(h(3), "d".to_owned()), (h(4), "e".to_owned())]);
+
+        assert_eq!(
+            entities.to_btreemap(),
+            expected.iter().map(|(id, data)| (*id, data)).collect()
+        );
+        assert_eq!(entities.into_btreemap(), expected);
+    }
+
+    #[test]
+    fn from_sparse() {
+        let mut sparse = SparseEntities::default();
+        ['a', 'b', 'c', 'd'].into_iter().for_each(|c| {
+            sparse.alloc(c);
+        });
+        sparse.remove(1u8);
+        sparse.remove(3);
+
+        let mut entities = Entities::from_sparse(sparse);
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[h(0)], 'a');
+        assert_eq!(entities[h(2)], 'c');
+        assert_eq!(entities.get(h(3)), None);
+        assert_eq!(entities.alloc('e'), h(4));
+
+        let sparse = SparseEntities::from_entities(entities);
+        assert_eq!(sparse.len(), 3);
+        assert_eq!(sparse[0], 'a');
+        assert_eq!(sparse[2], 'c');
+        assert_eq!(sparse[4], 'e');
+    }
+
+    #[test]
+    fn clear() {
+        let mut entities = Entities::<u8, usize>::populate_defaults(5);
+
+        entities.clear();
+        assert!(entities.is_empty());
+        assert_eq!(entities.get(h(0)), None);
+        assert_eq!(entities.alloc(1), h(5));
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn clear_and_reset() {
+        let mut entities = Entities::<u8, usize>::populate_defaults(5);
+
+        entities.clear_and_reset();
+        assert!(entities.is_empty());
+        assert_eq!(entities.get(h(0)), None);
+        assert_eq!(entities.alloc(1), h(0));
+        assert_eq!(entities[h(0)], 1);
+    }
+
+    #[test]
+    fn alloc_at_end() {
+        let mut entities = Entities::<u8, usize>::populate_defaults(5);
+        entities.remove(h(1));
+
+        let a = entities.alloc_at_end(10);
+        let b = entities.alloc_at_end(11);
+        assert_eq!(entities.physical_id(a), Some(5));
+        assert_eq!(entities.physical_id(b), Some(6));
+
+        // physical order matches allocation order
+        assert!(entities.iter().eq(&[0, 0, 0, 0, 10, 11]));
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut entities: Entities<u32, u32> = Default::default();
+        let ids: Vec<_> = (0..2000).map(|i| entities.alloc(i)).collect();
+        ids.iter().skip(100).for_each(|id| {
+            entities.remove(*id);
+        });
+
+        let vtable_capacity = entities.vtable.capacity();
+        entities.shrink_to_fit();
+
+        assert!(entities.vtable.capacity() < vtable_capacity);
+        assert!(entities.data.is_compact());
+        assert!(entities.data.allocated_capacity() < 2000);
+
+        // ids are still valid
+        assert_eq!(entities.len(), 100);
+        assert!(ids.iter().take(100).all(|id| entities[*id] == id.raw()));
+    }
+
+    #[test]
+    fn with_hasher() {
+        type SipBuildHasher = BuildHasherDefault<DefaultHasher>;
+
+        fn build() -> Entities<u16, u16, SipBuildHasher> {
+            let mut entities = Entities::with_hasher(SipBuildHasher::default());
+            (0..1000).for_each(|i| {
+                entities.alloc(i);
+            });
+            (0..1000).step_by(3).for_each(|i| {
+                entities.remove(h(i));
+            });
+            entities
+        }
+
+        let entities = build();
+        assert_eq!(entities.len(), 666);
+        assert!(entities.iter_with_id().all(|(id, data)| id.raw() == *data));
+
+        // the hasher has fixed keys, so the iteration order is reproducible
+        assert!(entities.iter_with_id().eq(build().iter_with_id()));
+
+        let sparse = SparseEntities::from_entities(entities);
+        let entities = Entities::from_sparse(sparse);
+        assert!(entities
+            .iter_sorted()
+            .eq(build().iter_sorted().collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn iter_with_id_physical() {
+        let mut entities: Entities<u8, char> = Default::default();
+        let ids: Vec<_> = "abcdefgh".chars().map(|c| entities.alloc(c)).collect();
+        entities.remove(ids[1]);
+        entities.remove(ids[2]);
+        entities.remove(ids[4]); // triggers coalesce, which moves items around
+
+        let physical: Vec<_> = entities.iter_with_id_physical().collect();
+        assert!(physical.iter().map(|(_, data)| *data).eq(entities.iter()));
+        assert!(physical
+            .iter()
+            .all(|(id, data)| entities.get(*id) == Some(data)));
+        assert_eq!(physical.len(), entities.len());
+
+        let mut visited = Vec::new();
+        entities.for_each_physical(|id, data| visited.push((id, data)));
+        assert_eq!(visited, physical);
+    }
+
+    #[test]
+    fn parallel_vecs() {
+        let mut entities: Entities<u8, char> = Default::default();
+        let ids: Vec<_> = "abcdefgh".chars().map(|c| entities.alloc(c)).collect();
+        entities.remove(ids[0]);
+        entities.remove(ids[5]);
+
+        let (ids, data) = entities.to_parallel_vecs();
+        assert_eq!(ids.len(), data.len());
+        assert!(ids.iter().zip(&data).all(|(id, c)| entities[*id] == **c));
+
+        let data: Vec<_> = data.into_iter().copied().collect();
+        assert_eq!(entities.into_parallel_vecs(), (ids, data));
+    }
+
+    #[test]
+    fn coalesce_budgeted() {
+        let mut entities: Entities<u16, u16> = Default::default();
+        let ids: Vec<_> = (0..1000).map(|i| entities.alloc(i)).collect();
+
+        // fewer dead slots than log(n) don't trigger the automatic coalesce
+        let removed = [3, 100, 501, 700, 998];
+        removed.iter().for_each(|i| {
+            entities.remove(ids[*i]);
+        });
+        assert_eq!(entities.data.dead_count(), removed.len());
+
+        let mut calls = 1;
+        while !entities.coalesce_budgeted(2) {
+            calls += 1;
+        }
+        assert_eq!(calls, 2);
+        assert!(entities.data.is_compact());
+
+        assert!(ids
+            .iter()
+            .enumerate()
+            .all(|(i, id)| entities.get(*id) == (!removed.contains(&i)).then_some(&id.raw())));
+
+        // reused and relocated slots are still tracked in a later pass
+        let extra = entities.alloc(5000);
+        entities.remove(ids[10]);
+        entities.remove(ids[20]);
+        while !entities.coalesce_budgeted(1) {}
+        assert_eq!(entities[extra], 5000);
+        assert_eq!(entities[ids[999]], 999);
+        assert_eq!(entities[ids[997]], 997);
+    }
+
     #[test]
     fn coalesce_1() {
         let mut entities: Entities<u8, u8> = Default::default();
         (0..255).for_each(|i| {
-            assert_eq!(entities.alloc(i), i);
+            assert_eq!(entities.alloc(i), h(i));
         });
 
-        entities.remove(27);
-        entities.remove(254);
-        entities.remove(15);
-        entities.remove(252);
-        entities.remove(251);
-        entities.remove(253);
+        entities.remove(h(27));
+        entities.remove(h(254));
+        entities.remove(h(15));
+        entities.remove(h(252));
+        entities.remove(h(251));
+        entities.remove(h(253));
 
         entities.coalesce();
 
@@ -364,41 +1231,41 @@ mod tests {
     fn coalesce_2() {
         let mut entities: Entities<u8, u8> = Default::default();
         (0..255).for_each(|i| {
-            assert_eq!(entities.alloc(i), i);
+            assert_eq!(entities.alloc(i), h(i));
         });
 
-        entities.remove(27);
-        entities.remove(15);
-
-        entities.remove(250);
-        entities.remove(232);
-        entities.remove(231);
-        entities.remove(254);
-        entities.remove(252);
-        entities.remove(251);
-        entities.remove(25);
-        entities.remove(253);
-        entities.remove(229);
-        entities.remove(233);
-        entities.remove(234);
-        entities.remove(235);
-        entities.remove(236);
-        entities.remove(237);
-        entities.remove(238);
-        entities.remove(239);
-        entities.remove(240);
-        entities.remove(35);
-        entities.remove(241);
-        entities.remove(242);
-        entities.remove(243);
-        entities.remove(245);
-        entities.remove(244);
-        entities.remove(246);
-        entities.remove(247);
-        entities.remove(248);
-        entities.remove(34);
-        entities.remove(249);
-        entities.remove(30);
+        entities.remove(h(27));
+        entities.remove(h(15));
+
+        entities.remove(h(250));
+        entities.remove(h(232));
+        entities.remove(h(231));
+        entities.remove(h(254));
+        entities.remove(h(252));
+        entities.remove(h(251));
+        entities.remove(h(25));
+        entities.remove(h(253));
+        entities.remove(h(229));
+        entities.remove(h(233));
+        entities.remove(h(234));
+        entities.remove(h(235));
+        entities.remove(h(236));
+        entities.remove(h(237));
+        entities.remove(h(238));
+        entities.remove(h(239));
+        entities.remove(h(240));
+        entities.remove(h(35));
+        entities.remove(h(241));
+        entities.remove(h(242));
+        entities.remove(h(243));
+        entities.remove(h(245));
+        entities.remove(h(244));
+        entities.remove(h(246));
+        entities.remove(h(247));
+        entities.remove(h(248));
+        entities.remove(h(34));
+        entities.remove(h(249));
+        entities.remove(h(30));
 
         entities.coalesce();
 
@@ -414,9 +1281,9 @@ mod tests {
             entities.alloc(c);
         });
 
-        entities.remove(2);
-        entities.remove(3);
-        entities.remove(1);
+        entities.remove(h(2));
+        entities.remove(h(3));
+        entities.remove(h(1));
 
         assert_eq!(entities.len(), 2);
         assert_eq!(
@@ -425,4 +1292,242 @@ mod tests {
         );
         assert_eq!(entities.data.capacity(), 2); // coalesce() was called since we removed a majority of items.
     }
+
+    #[test]
+    fn reinsert() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        (0..10).for_each(|i| {
+            entities.alloc(i);
+        });
+        let removed: Vec<_> = [3, 6, 9]
+            .into_iter()
+            .map(|i| (h(i), entities.remove(h(i)).unwrap()))
+            .collect();
+        entities.coalesce();
+
+        removed.into_iter().rev().for_each(|(id, data)| {
+            assert_eq!(entities.reinsert(id, data), Ok(()));
+        });
+        assert!((0..10).all(|i| entities[h(i)] == i));
+        assert_eq!(entities.reinsert(h(9), 0), Err(ReinsertError::Occupied));
+        assert_eq!(entities.reinsert(h(10), 0), Err(ReinsertError::NeverIssued));
+
+        // the sequence carries on
+        assert_eq!(entities.alloc(10), h(10));
+    }
+
+    #[test]
+    fn count_by() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..20).map(|i| entities.alloc(i)).collect();
+        assert_eq!(entities.count_by(|data| *data >= 10), 10);
+
+        ids.iter().step_by(2).for_each(|id| {
+            entities.remove(*id);
+        });
+        assert_eq!(entities.count_by(|data| *data >= 10), 5);
+        assert_eq!(entities.count_by(|_| true), entities.len());
+    }
+
+    #[test]
+    fn alloc_near() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..100).map(|i| entities.alloc(i)).collect();
+        [10, 50, 90].into_iter().for_each(|i| {
+            entities.remove(ids[i]);
+        });
+        let physical = |entities: &Entities<u8, u8>, id| entities.physical_id(id).unwrap();
+
+        let near = entities.alloc_near(100, ids[48]);
+        assert_eq!(physical(&entities, near), 50);
+        let near = entities.alloc_near(101, ids[15]);
+        assert_eq!(physical(&entities, near), 10);
+
+        // the end of the storage is closer than slot 90
+        let near = entities.alloc_near(102, ids[99]);
+        assert_eq!(physical(&entities, near), 100);
+
+        // a dead hint falls back to alloc()
+        let dead = ids[20];
+        assert_eq!(entities.remove(dead), Some(20));
+        let id = entities.alloc_near(103, dead);
+        assert_eq!(entities[id], 103);
+        assert_eq!(entities.len(), 100);
+    }
+
+    #[test]
+    fn alloc_compacting() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..100).map(|i| entities.alloc(i)).collect();
+        [10, 50, 90].into_iter().for_each(|i| {
+            entities.remove(ids[i]);
+        });
+
+        let new_ids: Vec<_> = (0..3)
+            .map(|i| entities.alloc_compacting(200 + i, 0.0))
+            .collect();
+        let physical: Vec<_> = new_ids
+            .iter()
+            .map(|&id| entities.physical_id(id).unwrap())
+            .collect();
+        assert_eq!(physical, [97, 98, 99]);
+
+        assert_eq!(entities.len(), 100);
+        ids.iter()
+            .enumerate()
+            .filter(|(i, _)| ![10, 50, 90].contains(i))
+            .for_each(|(i, &id)| assert_eq!(entities[id], i as u8));
+        new_ids
+            .iter()
+            .zip(200..)
+            .for_each(|(&id, data)| assert_eq!(entities[id], data));
+    }
+
+    #[test]
+    fn alloc_compacting_threshold() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..100).map(|i| entities.alloc(i)).collect();
+        [10, 50].into_iter().for_each(|i| {
+            entities.remove(ids[i]);
+        });
+
+        // 2 dead slots among 98 items is below 5%, so the holes get filled as usual
+        let id = entities.alloc_compacting(200, 0.05);
+        assert_eq!(entities.physical_id(id), Some(50));
+        assert_eq!(entities.data.capacity(), 100);
+
+        // 1 dead slot among 99 items is above 0.5%
+        let id = entities.alloc_compacting(201, 0.005);
+        assert_eq!(entities.physical_id(id), Some(99));
+        assert!(entities.data.is_compact());
+    }
+
+    #[test]
+    fn changelog() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let a = entities.alloc(0);
+        entities.alloc(1);
+        assert!(entities.drain_changelog().is_empty());
+
+        entities.enable_changelog();
+        let ids: Vec<_> = (2..20).map(|i| entities.alloc(i)).collect();
+        // enough removals to coalesce, which doesn't show up in the changelog
+        ids[..8].iter().for_each(|&id| {
+            entities.remove(id);
+        });
+        let removed = entities.remove(a).unwrap();
+        entities.reinsert(a, removed).unwrap();
+
+        let expected: Vec<_> = ids
+            .iter()
+            .map(|&id| ChangeEvent::Added(id))
+            .chain(ids[..8].iter().map(|&id| ChangeEvent::Removed(id)))
+            .chain([ChangeEvent::Removed(a), ChangeEvent::Added(a)])
+            .collect();
+        assert_eq!(entities.drain_changelog(), expected);
+
+        // missing ids aren't recorded
+        assert_eq!(entities.remove(ids[0]), None);
+        assert!(entities.drain_changelog().is_empty());
+
+        let alive: Vec<_> = entities.to_btreemap().into_keys().collect();
+        entities.clear();
+        let expected: Vec<_> = alive.into_iter().map(ChangeEvent::Removed).collect();
+        assert_eq!(entities.drain_changelog(), expected);
+
+        entities.disable_changelog();
+        entities.alloc(0);
+        assert!(entities.drain_changelog().is_empty());
+    }
+
+    #[test]
+    fn physical() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..20).map(|i| entities.alloc(i)).collect();
+        entities.remove(ids[3]);
+
+        ids.iter().filter(|&&id| id != ids[3]).for_each(|&id| {
+            let physical_id = entities.physical_id(id).unwrap() as usize;
+            assert_eq!(entities.physical(physical_id), entities.get(id));
+        });
+        assert_eq!(entities.physical(3), None);
+        assert_eq!(entities.physical(20), None);
+    }
+
+    #[test]
+    fn to_vec() {
+        let mut entities: Entities<u16, u16> = Default::default();
+        let ids: Vec<_> = (0..100).map(|i| entities.alloc(i)).collect();
+        (0..100).step_by(3).for_each(|i| {
+            entities.remove(ids[i]);
+        });
+
+        let snapshot = entities.to_vec();
+        assert!(snapshot.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(snapshot
+            .iter()
+            .map(|(id, data)| (*id, data))
+            .eq(entities.iter_sorted()));
+
+        // the order doesn't depend on the physical layout
+        let mut other = entities.clone();
+        other.shrink_to_fit();
+        assert_eq!(other.to_vec(), snapshot);
+    }
+
+    #[test]
+    fn is_stale() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..20).map(|i| entities.alloc(i)).collect();
+        (0..20).step_by(2).for_each(|i| {
+            entities.remove(ids[i]);
+        });
+
+        // coalescing along the way doesn't affect the handles
+        ids.iter().enumerate().for_each(|(i, &id)| {
+            assert_eq!(entities.is_stale(id), i % 2 == 0);
+            assert_eq!(entities.is_stale(id), entities.get(id).is_none());
+        });
+        assert!(!entities.is_stale(h(20)));
+        assert!(!entities.is_stale(h(u8::MAX)));
+
+        entities.clear_and_reset();
+        assert!(!entities.is_stale(ids[1]));
+    }
+
+    #[test]
+    fn clone_isolated_from_coalesce() {
+        let mut entities: Entities<u8, String> = Default::default();
+        let ids: Vec<_> = (0..50).map(|i| entities.alloc(i.to_string())).collect();
+        ids[..3].iter().for_each(|&id| {
+            entities.remove(id);
+        });
+        assert!(entities.data.dead_count() > 0);
+
+        let physical_before: Vec<_> = ids.iter().map(|&id| entities.physical_id(id)).collect();
+        let snapshot = entities.to_vec();
+
+        // coalescing rebuilds the reverse mapping of the clone only
+        let mut cloned = entities.clone();
+        cloned.shrink_to_fit();
+        assert_eq!(cloned.data.dead_count(), 0);
+        ids[3..10].iter().for_each(|&id| {
+            cloned.remove(id);
+        });
+        cloned[ids[20]].push('!');
+
+        let physical_after: Vec<_> = ids.iter().map(|&id| entities.physical_id(id)).collect();
+        assert_eq!(physical_after, physical_before);
+        assert_eq!(entities.to_vec(), snapshot);
+        assert!(entities.data.dead_count() > 0);
+
+        assert_eq!(cloned.len(), 40);
+        assert_eq!(cloned[ids[20]], "20!");
+        assert_ne!(
+            ids.iter()
+                .map(|&id| cloned.physical_id(id))
+                .collect::<Vec<_>>(),
+            physical_before
+        );
+    }
 }
@@ -0,0 +1,199 @@
+use std::mem;
+
+use stable_id_traits::CastUsize;
+
+use crate::{Eids, EidsIndex, IdSpace};
+
+impl<IndexT, DataT> Default for IdSpace<IndexT, DataT>
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    fn default() -> Self {
+        Self {
+            ids: Eids::default(),
+            data: Vec::new(),
+            count: 0,
+        }
+    }
+}
+
+impl<IndexT, DataT> IdSpace<IndexT, DataT>
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    /// Number of items in this data structure.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read-only access to the underlying [`Eids`].
+    pub fn as_eids(&self) -> &Eids<IndexT> {
+        &self.ids
+    }
+
+    /// Store `data` under a new id, see [`Eids::claim()`].
+    pub fn insert(&mut self, data: DataT) -> IndexT {
+        let id = self.ids.claim();
+        let position = id.cast_to();
+
+        if position == self.data.len() {
+            self.data.push(Some(data));
+        } else {
+            debug_assert!(self.data[position].is_none(), "recycled id is in use");
+            self.data[position] = Some(data);
+        }
+        self.count += 1;
+
+        id
+    }
+
+    pub fn get(&self, id: IndexT) -> Option<&DataT> {
+        self.data.get(id.cast_to()).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, id: IndexT) -> Option<&mut DataT> {
+        self.data.get_mut(id.cast_to()).and_then(Option::as_mut)
+    }
+
+    /// Take the item of `id` out and free the id for reuse. Returns `None` if there's no such item.
+    pub fn remove(&mut self, id: IndexT) -> Option<DataT> {
+        let data = self.data.get_mut(id.cast_to())?.take()?;
+        self.ids.unclaim(id);
+        self.count -= 1;
+
+        Some(data)
+    }
+
+    /// Iterate the items in ascending order of their ids.
+    pub fn iter_with_id(&self) -> impl Iterator<Item = (IndexT, &DataT)> {
+        self.data.iter().enumerate().filter_map(|(position, data)| {
+            data.as_ref()
+                .map(|data| (IndexT::cast_from(position), data))
+        })
+    }
+
+    /**
+    Pack the items together by moving them into the freed ids, see [`Eids::coalesce()`].
+    The data is moved along, so only the references held elsewhere need to be updated through `f(old_id, new_id)`.
+    */
+    pub fn coalesce<F>(&mut self, mut f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        let data = &mut self.data;
+        self.ids.coalesce(|old_id, new_id| {
+            data.swap(old_id.cast_to(), new_id.cast_to());
+            f(old_id, new_id);
+        });
+
+        // every id below the length is now issued and holds an item
+        self.data.truncate(self.count);
+        debug_assert!(self.data.iter().all(Option::is_some));
+    }
+
+    /// Remove all items and start over from id 0.
+    pub fn clear(&mut self) {
+        self.ids = Eids::default();
+        self.data.clear();
+        self.count = 0;
+    }
+
+    /// Move the items out in ascending order of their ids, leaving the collection empty.
+    pub fn drain(&mut self) -> impl Iterator<Item = (IndexT, DataT)> {
+        self.ids = Eids::default();
+        self.count = 0;
+
+        mem::take(&mut self.data)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, data)| data.map(|data| (IndexT::cast_from(position), data)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::IdSpace;
+    use crate::eids::freed_store::FreedStore;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut space: IdSpace<u8, u32> = Default::default();
+        let ids: Vec<_> = (0..10).map(|i| space.insert(i)).collect();
+        assert!(ids.iter().copied().eq(0..10));
+
+        assert_eq!(space.remove(7), Some(7));
+        assert_eq!(space.remove(2), Some(2));
+        assert_eq!(space.remove(2), None);
+        assert_eq!(space.remove(100), None);
+        assert_eq!(space.len(), 8);
+        assert_eq!(space.get(2), None);
+
+        // smallest freed id first
+        assert_eq!(space.insert(20), 2);
+        assert_eq!(space.insert(70), 7);
+        assert_eq!(space.insert(10), 10);
+        *space.get_mut(10).unwrap() += 1;
+        assert_eq!(space.get(10), Some(&11));
+        assert_eq!(space.len(), 11);
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut space: IdSpace<u16, u32> = Default::default();
+        (0..100).for_each(|i| {
+            space.insert(i);
+        });
+        (0..100).step_by(3).for_each(|id| {
+            space.remove(id);
+        });
+        let mut expected: BTreeMap<_, _> =
+            space.iter_with_id().map(|(id, data)| (id, *data)).collect();
+
+        // replay the relocations in order, like references held elsewhere would be updated
+        space.coalesce(|old_id, new_id| {
+            let data = expected.remove(&old_id).unwrap();
+            assert!(expected.insert(new_id, data).is_none());
+        });
+
+        assert_eq!(space.len(), 66);
+        assert!(space
+            .iter_with_id()
+            .map(|(id, data)| (id, *data))
+            .eq(expected));
+        assert_eq!(space.insert(1000), 66);
+    }
+
+    #[test]
+    fn drain() {
+        let mut space: IdSpace<u8, char> = Default::default();
+        "abc".chars().for_each(|c| {
+            space.insert(c);
+        });
+        space.remove(1);
+
+        assert!(space.drain().eq([(0, 'a'), (2, 'c')]));
+        assert!(space.is_empty());
+        assert_eq!(space.insert('d'), 0);
+    }
+
+    #[test]
+    fn small_ids_use_the_bitset() {
+        let is_dense =
+            |space: &IdSpace<u16, u32>| matches!(space.ids.freed, FreedStore::Dense { .. });
+
+        let mut space: IdSpace<u16, u32> = Default::default();
+        assert!(is_dense(&space));
+        space.insert(1);
+        space.clear();
+        assert!(is_dense(&space));
+        space.insert(2);
+        space.drain().for_each(drop);
+        assert!(is_dense(&space));
+    }
+}
@@ -24,6 +24,7 @@ let x: stable_id::SparseEntities<Id32, String> = Default::default();
 let x: stable_id::Entities<Id32, String> = Default::default();
 let x: stable_id::Tec<Id32, String> = Default::default();
 ```
+Or use [`define_id!`] to declare such a struct in one line.
 
 # Use cases
 | Struct                | Type          | Suggestion    | Description |
@@ -33,23 +34,42 @@ let x: stable_id::Tec<Id32, String> = Default::default();
 | [`Entities`]          | Collection    | Dense data    | The go-to collection of this library.
 | [`SparseEntities`]    | Collection    | Sparse data   | You want mix sequence (ids not recycled) and HashMap together. |
 | [`Tec`]               | Collection    | Dense data    | You want to use a vec to store data, but need constant entity removal. [`Tec`] reclaims the spaces for you as you insert more new items.
+| [`TaggedTec`]         | Collection    | Dense data    | Same as [`Tec`], with a small tag attached to each slot but kept apart from the data.
+| [`IdSpace`]           | Collection    | Dense data    | An [`Eids`] and a `Vec` kept in sync, i.e. like [`Tec`] but recycling the smallest freed id first.
  */
-use std::collections::BTreeSet;
+use std::{
+    hash::{BuildHasherDefault, Hash},
+    sync::{Mutex, OnceLock},
+};
 
-use rustc_hash::FxHashMap;
+// the hash map behind `Entities` and `SparseEntities`, see the `hashbrown` feature
+#[cfg(feature = "hashbrown")]
+use hashbrown::hash_map;
+#[cfg(not(feature = "hashbrown"))]
+use std::collections::hash_map;
+
+use hash_map::HashMap;
+
+use eids::freed_store::FreedStore;
+use rustc_hash::FxHasher;
+use tomb_vec::free_link::FreeLink;
 
 pub use derive_stable_id::StableId;
 pub use stable_id_traits::*;
 
+mod define_id;
 mod eids;
 mod entities;
+mod id_space;
 mod sequence;
 mod sparse_entities;
+mod tagged_tec;
 mod tomb_vec;
+mod transaction;
 
 /**
 Stands for Entity Id generator (ids are redeemable).
-Basically a counter with a B-tree based free "set" (list).
+Basically a counter with a free "set" (list), which is a bitset for ids of up to 16 bits, and a B-tree otherwise.
 
 # Use case
 - you want to recycle ids due to frequent entity removal
@@ -68,13 +88,59 @@ entities.unclaim(id);
 See [`Self::coalesce()`] if you want to pack ids together, like when you're trying to tighten up an array and
 saving it into a database/save file (i.e. when game players are saving their progress).
 */
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Eids<IndexT>
 where
     IndexT: Ord,
 {
-    freed: BTreeSet<IndexT>,
+    freed: FreedStore<IndexT>,
     next: IndexT,
+    recycle_order: RecycleOrder,
+    /// unclaimed ids in the order they were freed, only maintained under [`RecycleOrder::MostRecent`].
+    /// May contain stale entries which are skipped lazily.
+    recently_freed: Vec<IndexT>,
+    /// `next` can't go past the maximum value, so this tells whether the maximum value itself has been issued
+    #[cfg(feature = "full-range")]
+    exhausted: bool,
+}
+
+/**
+An id claimed by [`Eids::claim_scoped()`], which is unclaimed when the guard is dropped.
+Dereferences to the id.
+*/
+pub struct ClaimGuard<'a, IndexT>
+where
+    IndexT: EidsIndex,
+{
+    eids: &'a mut Eids<IndexT>,
+    id: IndexT,
+}
+
+/**
+Decides which freed id gets handed out by [`Eids::claim()`].
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RecycleOrder {
+    /// Always reuse the smallest freed id. This is deterministic regardless of the order ids were unclaimed.
+    #[default]
+    Smallest,
+    /// Reuse the most recently unclaimed id, which is likely still hot in the cache.
+    /// This costs an extra stack of freed ids.
+    MostRecent,
+}
+
+/**
+Decides how [`Eids::coalesce_preferring()`] packs the ids.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CoalesceStrategy {
+    /// Move the largest issued ids into the freed ones until the ids are contiguous, i.e. [`Eids::coalesce()`].
+    /// The largest ids tend to belong to the newest entities, which are the ones that get relocated.
+    #[default]
+    RelocateHighest,
+    /// Never relocate anything, and only take back the freed ids at the end of the issued range.
+    /// The freed ids in between stay freed, so the ids may remain fragmented.
+    RelocateNone,
 }
 
 /**
@@ -101,11 +167,154 @@ pub struct Sequence<IndexT> {
     counter: IndexT,
 }
 
+/**
+Returned by [`Sequence::next_value_checked()`] when there are no more ids to issue.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Exhausted;
+
+/**
+Common interface of the id generators, i.e. [`Eids`] (ids are recycled) and [`Sequence`] (ids are never recycled),
+so that code can be generic over the allocation strategy.
+
+```
+use stable_id::{Eids, IdAllocator, Sequence};
+
+fn spawn_and_despawn(allocator: &mut dyn IdAllocator<u8>) -> u8 {
+    let id = allocator.alloc();
+    allocator.free(id);
+    allocator.peek_next()
+}
+
+assert_eq!(spawn_and_despawn(&mut Eids::default()), 0);
+assert_eq!(spawn_and_despawn(&mut Sequence::default()), 1);
+```
+*/
+pub trait IdAllocator<IndexT> {
+    /// Issue a new id.
+    fn alloc(&mut self) -> IndexT;
+
+    /// Give back an id. Whether it gets reused depends on the implementation.
+    fn free(&mut self, id: IndexT);
+
+    /// The id that the next call to [`Self::alloc()`] would return.
+    fn peek_next(&self) -> IndexT;
+}
+
+/**
+Every bound the collections of this crate may need from an id type, so that generic code doesn't have to spell them out.
+It's implemented for every type that satisfies the bounds, including the unsigned integers and the structs that
+derive [`StableId`].
+
+Each collection only asks for its own subset, named by [`TecIndex`], [`EidsIndex`], [`SequenceIndex`],
+[`SparseEntitiesIndex`], and [`EntitiesIndex`], which are implemented the same way.
+
+```
+use stable_id::{Entities, Handle, StableIndex};
+
+struct Named<IndexT: StableIndex> {
+    names: Entities<IndexT, String>,
+}
+
+impl<IndexT: StableIndex> Named<IndexT> {
+    fn add(&mut self, name: &str) -> Handle<IndexT> {
+        self.names.alloc(name.to_owned())
+    }
+}
+
+let mut named = Named::<u16> { names: Default::default() };
+let id = named.add("player");
+assert_eq!(named.names[id], "player");
+```
+*/
+pub trait StableIndex: EntitiesIndex + EidsIndex {}
+
+impl<T> StableIndex for T where T: EntitiesIndex + EidsIndex {}
+
+/// The bounds of the id type of a [`Tec`], see [`StableIndex`].
+pub trait TecIndex: CastUsize + Ord + Copy + Maximum {}
+
+impl<T> TecIndex for T where T: CastUsize + Ord + Copy + Maximum {}
+
+/// The bounds of the id type of an [`Eids`], see [`StableIndex`].
+pub trait EidsIndex: Successor + Predecessor + Copy + Ord + Maximum {}
+
+impl<T> EidsIndex for T where T: Successor + Predecessor + Copy + Ord + Maximum {}
+
+/// The bounds of the id type of a [`Sequence`], see [`StableIndex`].
+pub trait SequenceIndex: Successor + Copy {}
+
+impl<T> SequenceIndex for T where T: Successor + Copy {}
+
+/// The bounds of the id type of a [`SparseEntities`], see [`StableIndex`].
+pub trait SparseEntitiesIndex: SequenceIndex + Hash + Eq + Default {}
+
+impl<T> SparseEntitiesIndex for T where T: SequenceIndex + Hash + Eq + Default {}
+
+/// The bounds of the id type of an [`Entities`], see [`StableIndex`].
+pub trait EntitiesIndex: TecIndex + SparseEntitiesIndex {}
+
+impl<T> EntitiesIndex for T where T: TecIndex + SparseEntitiesIndex {}
+
+/**
+Common interface of the keyed collections, i.e. [`Tec`], [`Entities`], and [`SparseEntities`],
+so that code can be generic over the storage strategy.
+
+```
+use std::fmt::Debug;
+use stable_id::{Entities, SparseEntities, StableCollection, Tec};
+
+fn spawn_and_despawn<IndexT, C>(mut collection: C)
+where
+    IndexT: Copy + Debug + PartialEq,
+    C: StableCollection<IndexT, char>,
+{
+    let a = collection.alloc('a');
+    let b = collection.alloc('b');
+
+    assert_eq!(collection.remove(a), Some('a'));
+    assert_eq!(collection.remove(a), None);
+    *collection.get_mut(b).unwrap() = 'c';
+
+    assert_eq!(collection.get(b), Some(&'c'));
+    assert_eq!(collection.len(), 1);
+    assert_eq!(collection.iter_with_id().collect::<Vec<_>>(), [(b, &'c')]);
+}
+
+spawn_and_despawn(Tec::<u8, _>::default());
+spawn_and_despawn(Entities::<u8, _>::default()); // ids are Handle<u8>
+spawn_and_despawn(SparseEntities::<u8, _>::default());
+```
+*/
+pub trait StableCollection<IndexT, DataT> {
+    /// Store `data` and return its id.
+    fn alloc(&mut self, data: DataT) -> IndexT;
+
+    fn get(&self, index: IndexT) -> Option<&DataT>;
+
+    fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT>;
+
+    /// Remove the item with the given id, or return `None` if there is no such item.
+    fn remove(&mut self, index: IndexT) -> Option<DataT>;
+
+    /// Number of items in the collection.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate every items along with their ids. The order depends on the collection.
+    fn iter_with_id<'a>(&'a self) -> impl Iterator<Item = (IndexT, &'a DataT)>
+    where
+        DataT: 'a;
+}
+
 /// inspired by https://github.com/fitzgen/generational-arena/blob/72975c8355949c2338976d944e047c9d9f447174/src/lib.rs#L178
 /// but without the generation stuff.
 #[derive(Clone, Debug)]
 pub(crate) enum Slot<DataT, IndexT> {
-    Dead { next_free: IndexT },
+    Dead { next_free: FreeLink<IndexT> },
     Alive(DataT),
 }
 
@@ -136,17 +345,128 @@ assert_eq!(storage.alloc(Data {field: 123}), Id(0));
 assert_eq!(storage.get(Id(0)).unwrap().field, 123);
 ```
 */
-#[derive(Clone)]
 pub struct Tec<IndexT, DataT> {
     vec: Vec<Slot<DataT, IndexT>>,
     /// invariants: the free index must be either
     ///      - pointer some dead slot within the `vec`
-    ///      - or the sentinal value of Maximum::maximum()
+    ///      - or the sentinal that marks the end of the free list (see the `free_link` module)
     /// In other words, the `vec` cannot have trailing dead slots
-    next_free: IndexT,
+    next_free: FreeLink<IndexT>,
     count: usize,
+    /// number of items ever allocated, see [`Tec::total_allocated()`]
+    total_allocated: u64,
+    /// see [`Tec::on_change()`]; the mutex only lets `Tec` stay `Sync` without requiring `Sync` observers, it's never locked
+    observer: Option<Mutex<Box<TecObserver<IndexT>>>>,
+    /// forbids allocating and removing items, see [`Tec::seal()`]
+    sealed: bool,
+    /// lazily-built ids of living slots, see [`Tec::live_ids_cached()`]; reset whenever a slot is born or dies
+    live_ids: OnceLock<Vec<IndexT>>,
+    /// number of times each slot has lost its item, see [`Tec::generation()`]; only grows when a slot is retired
+    #[cfg(feature = "debug-tracking")]
+    generations: Vec<u32>,
+    /// positions of the living slots, consulted by the iterators
+    #[cfg(feature = "fast-iter")]
+    live: tomb_vec::live_bits::LiveBits,
+}
+
+/**
+Changes reported to the observer registered through [`Tec::on_change()`].
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TecEvent<IndexT> {
+    /// A slot became alive.
+    Allocated(IndexT),
+    /// A living slot got tombstoned.
+    Removed(IndexT),
+    /// A living item was moved by [`Tec::coalesce()`].
+    Relocated { from: IndexT, to: IndexT },
 }
 
+/// The callback type accepted by [`Tec::on_change()`].
+pub type TecObserver<IndexT> = dyn FnMut(TecEvent<IndexT>) + Send;
+
+/**
+A read-only, filtered window into a [`Tec`], created by [`Tec::view()`]. Only the living items that satisfy
+the predicate are visible through the view, and nothing is copied.
+*/
+#[derive(Clone)]
+pub struct TecView<'a, IndexT, DataT, P> {
+    tec: &'a Tec<IndexT, DataT>,
+    pred: P,
+}
+
+/**
+A mutable window over a contiguous range of ids of a [`Tec`], created by [`Tec::split_at_mut()`].
+Views never overlap, so they can be handed to different threads.
+*/
+pub struct TecMutView<'a, IndexT, DataT> {
+    slots: &'a mut [Slot<DataT, IndexT>],
+    /// the position of `slots[0]` in the whole storage
+    offset: usize,
+    #[cfg(feature = "fast-iter")]
+    live: &'a tomb_vec::live_bits::LiveBits,
+}
+
+/**
+A [`Tec`] with a small tag (like an archetype id) attached to every slot. The tags are kept in a separate `Vec`
+that moves in lockstep with the slots, including the relocations done by [`TaggedTec::coalesce()`],
+so iterating over the data alone doesn't drag the tags along.
+
+```
+use stable_id::TaggedTec;
+
+let mut storage: TaggedTec<u8, String, u8> = Default::default();
+let id = storage.alloc_with_tag("player".to_owned(), 3);
+assert_eq!(storage.get_tag(id), Some(&3));
+
+storage.set_tag(id, 7);
+assert_eq!(storage.get_tag(id), Some(&7));
+```
+*/
+#[derive(Clone, Debug)]
+pub struct TaggedTec<IndexT, DataT, TagT> {
+    tec: Tec<IndexT, DataT>,
+    /// invariant: one tag per slot, i.e. `tags.len() == tec.capacity()`; tags of dead slots are reset to the default
+    tags: Vec<TagT>,
+}
+
+/**
+An [`Eids`] paired with a `Vec` of the data indexed by the ids, so that the two can't get out of sync,
+especially when coalescing. Unlike [`Tec`], which reuses the most recently freed slot,
+freed ids are recycled smallest first (see [`RecycleOrder::Smallest`]), which keeps the data towards the front.
+
+```
+use stable_id::IdSpace;
+
+let mut space: IdSpace<u8, char> = Default::default();
+let a = space.insert('a');
+let b = space.insert('b');
+let c = space.insert('c');
+space.remove(a);
+space.remove(b);
+
+assert_eq!(space.insert('d'), a);
+
+let mut moved = Vec::new();
+space.coalesce(|old_id, new_id| moved.push((old_id, new_id)));
+assert_eq!(moved, [(c, b)]);
+assert_eq!(space.get(b), Some(&'c'));
+```
+*/
+#[derive(Clone)]
+pub struct IdSpace<IndexT, DataT>
+where
+    IndexT: Ord,
+{
+    ids: Eids<IndexT>,
+    /// invariant: one entry per issued id, i.e. `data.len()` is the number of ids issued by `ids`
+    data: Vec<Option<DataT>>,
+    count: usize,
+}
+
+/// The default hasher of [`SparseEntities`] and [`Entities`].
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
 /**
 This is a sandwich of HashMap and [`Sequence`].
 
@@ -157,12 +477,23 @@ This is a sandwich of HashMap and [`Sequence`].
 Use cases:
 - you're removing more entities than you are adding
 - you don't care about relaiming ids
+
+The hash map uses [`FxBuildHasher`] by default. It's fast, but its iteration order is an implementation detail
+that may change between versions of `rustc-hash`. If you need a reproducible order, e.g. for deterministic simulations,
+plug in your own [`BuildHasher`](std::hash::BuildHasher) through [`SparseEntities::with_hasher()`].
+
+The map is [`std::collections::HashMap`], or `hashbrown::HashMap` with the `hashbrown` feature,
+so that there's only one hash map implementation to compile if you're using `hashbrown` already.
 */
-pub struct SparseEntities<IndexT, DataT> {
-    data: FxHashMap<IndexT, DataT>,
+pub struct SparseEntities<IndexT, DataT, S = FxBuildHasher> {
+    data: HashMap<IndexT, DataT, S>,
     seq: Sequence<IndexT>,
 }
 
+/// The owning iterator of a [`SparseEntities`], in no particular order. It's the same type regardless of the
+/// `hashbrown` feature.
+pub struct IntoIter<IndexT, DataT>(hash_map::IntoIter<IndexT, DataT>);
+
 /**
 This is a lazily memory-compact version of [`SparseEntities`].
 Use cases are the same but there are different tradeoffs.
@@ -174,10 +505,146 @@ Use cases are the same but there are different tradeoffs.
   the internal id, a random access follows.
 - removing items is O([`Tec::remove()`]) = O(n lg n) though I have plans to make it O(n). An added benefits is [`remove()`] will also
   try to compact the memory by removing dead slots from [`Tec`] when there's a majority of dead slots -- it's another O(n) pass.
+
+Ids are issued as [`Handle`]s, so that they can't be confused with the physical ids of the backing [`Tec`].
+
+Like [`SparseEntities`], the hasher of the virtual table can be swapped through [`Entities::with_hasher()`].
 */
 #[derive(Clone)]
-pub struct Entities<IndexT, DataT> {
-    vtable: FxHashMap<IndexT, IndexT>, // virtual id -> physical id
+pub struct Entities<IndexT, DataT, S = FxBuildHasher> {
+    vtable: HashMap<IndexT, IndexT, S>, // virtual id -> physical id
+    /// physical id -> virtual id, only meaningful for the living slots; at least as long as the backing storage
+    rvtable: Vec<IndexT>,
     data: Tec<IndexT, DataT>,
     seq: Sequence<IndexT>,
+    /// `None` unless enabled by [`Entities::enable_changelog()`]
+    changelog: Option<Vec<ChangeEvent<IndexT>>>,
+}
+
+/**
+An item being added to or removed from an [`Entities`], as recorded by [`Entities::enable_changelog()`].
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeEvent<IndexT> {
+    Added(Handle<IndexT>),
+    Removed(Handle<IndexT>),
+}
+
+/**
+An id issued by [`Entities`]. Unlike the physical ids used by its backing [`Tec`] (see [`Entities::physical_id()`]),
+handles stay valid when [`Entities`] compacts itself, so they are safe to hold on to until the item is removed.
+
+The distinct type keeps the two kinds of ids from being mixed up:
+```compile_fail
+use stable_id::Entities;
+
+let mut entities: Entities<u8, char> = Default::default();
+let handle = entities.alloc('a');
+let physical_id = entities.physical_id(handle).unwrap();
+entities.get(physical_id); // a physical id is not a handle
+```
+```compile_fail
+use stable_id::{Entities, Tec};
+
+let mut tec: Tec<u8, char> = Default::default();
+let mut entities: Entities<u8, char> = Default::default();
+entities.remove(tec.alloc('a')); // neither is an id issued by a Tec
+```
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle<IndexT>(IndexT);
+
+/**
+A batch of changes to an [`Entities`], see [`Entities::transaction()`].
+Every change is logged, so that the batch can be undone as a whole.
+*/
+pub struct EntitiesTxn<'a, IndexT, DataT, S = FxBuildHasher> {
+    entities: &'a mut Entities<IndexT, DataT, S>,
+    /// the state of the id sequence before the transaction, so that ids allocated by an aborted transaction are reissued
+    checkpoint: IndexT,
+    log: Vec<TxnOp<IndexT, DataT>>,
+}
+
+/// A logged change of an [`EntitiesTxn`], along with what it takes to undo it.
+enum TxnOp<IndexT, DataT> {
+    Alloc(Handle<IndexT>),
+    Remove(Handle<IndexT>, DataT),
+    /// holds the value before the change
+    Set(Handle<IndexT>, DataT),
+}
+
+/**
+The reason why [`Entities::try_get()`] failed to find an item.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessError {
+    /// The id hasn't been issued by the collection.
+    NeverIssued,
+    /// The id was issued, but the item has been removed.
+    Removed,
+}
+
+/**
+What [`Tec::coalesce_stats()`] has done.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CoalesceStats {
+    /// The number of items that got relocated.
+    pub moved: usize,
+    /// The number of dead slots that got dropped.
+    pub reclaimed: usize,
+    /// [`Tec::capacity()`] before coalescing.
+    pub before_capacity: usize,
+    /// [`Tec::capacity()`] after coalescing.
+    pub after_capacity: usize,
+}
+
+/**
+Returned by [`Tec::with_capacity_checked()`] when `IndexT` is too small to address the requested capacity.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CapacityError {
+    /// The capacity that was asked for.
+    pub requested: usize,
+    /// The maximum number of items that `IndexT` can address.
+    pub max: usize,
+}
+
+/**
+The reason why [`Tec::reinsert()`] or [`Entities::reinsert()`] couldn't put the item back.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReinsertError {
+    /// The id refers to a living item.
+    Occupied,
+    /// The id is past the end of the storage, or can't be addressed by `IndexT`.
+    OutOfBounds,
+    /// The id hasn't been issued by the collection.
+    NeverIssued,
+}
+
+/**
+Returned by [`Tec::try_alloc()`] and [`Tec::try_remove()`] when the [`Tec`] is sealed, see [`Tec::seal()`].
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SealedError;
+
+/**
+Returned by [`Tec::extend_from_disjoint()`] when both collections have a living item under the same id.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OverlapError<IndexT> {
+    /// The first colliding id.
+    pub id: IndexT,
+}
+
+/**
+The reason why [`Tec::at()`] or [`Tec::at_mut()`] couldn't find an item.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IndexError {
+    /// The id is past the end of the storage.
+    OutOfBounds,
+    /// The slot exists, but its item has been removed.
+    Dead,
 }
@@ -1,18 +1,300 @@
-use stable_id_traits::Successor;
+use std::{error::Error, fmt::Display, iter, mem, ops::Range};
 
-use crate::Sequence;
+use stable_id_traits::{CastUsize, Maximum};
+
+use crate::{Eids, EidsIndex, Exhausted, IdAllocator, Sequence, SequenceIndex};
 
 impl<IndexT> Sequence<IndexT>
 where
-    IndexT: Successor + Clone + Copy,
+    IndexT: SequenceIndex,
 {
     pub const fn continue_from(start: IndexT) -> Self {
         Self { counter: start }
     }
 
+    /**
+    Same as [`Self::continue_from()`], for importing the counter of another allocator along with [`Eids::from_raw()`].
+    [`Self::peek_value()`] gives the counter back.
+    */
+    pub const fn from_raw(counter: IndexT) -> Self {
+        Self::continue_from(counter)
+    }
+
+    /** Returns the value that the next call to [`Self::next_value()`] would give, without advancing the counter. */
+    pub fn peek_value(&self) -> IndexT {
+        self.counter
+    }
+
     pub fn next_value(&mut self) -> IndexT {
         let ret = self.counter;
         self.counter = ret.next_value();
         ret
     }
 }
+
+impl<IndexT> Sequence<IndexT>
+where
+    IndexT: SequenceIndex + Maximum + Eq,
+{
+    /**
+    Similar to [`Self::next_value()`], but doesn't panic when the counter reaches `IndexT::max_value()`.
+    Instead, the maximum value is returned repeatedly, so once saturated the same id can be handed out more than once.
+    Check the returned value against the maximum if you care about that.
+
+    ```
+    use stable_id::Sequence;
+
+    let mut s = Sequence::continue_from(254u8);
+    assert_eq!(s.saturating_next_value(), 254);
+    assert_eq!(s.saturating_next_value(), 255);
+    assert_eq!(s.saturating_next_value(), 255);
+    ```
+    */
+    pub fn saturating_next_value(&mut self) -> IndexT {
+        if self.counter == IndexT::max_value() {
+            self.counter
+        } else {
+            self.next_value()
+        }
+    }
+
+    /**
+    Similar to [`Self::next_value()`], but starts over from `IndexT::default()` after handing out `IndexT::max_value()`,
+    so the ids cycle forever instead of running out.
+
+    **Warning**: once wrapped, ids that were issued before are issued again, so this is only meant for short-lived ids
+    (e.g. transient effects) where the caller makes sure that an id is no longer in use by the time it comes around.
+
+    ```
+    use stable_id::Sequence;
+
+    let mut s = Sequence::continue_from(254u8);
+    assert_eq!(s.wrapping_next_value(), 254);
+    assert_eq!(s.wrapping_next_value(), 255);
+    assert_eq!(s.wrapping_next_value(), 0);
+    ```
+    */
+    pub fn wrapping_next_value(&mut self) -> IndexT
+    where
+        IndexT: Default,
+    {
+        if self.counter == IndexT::max_value() {
+            mem::take(&mut self.counter)
+        } else {
+            self.next_value()
+        }
+    }
+
+    /**
+    Similar to [`Self::next_value()`], but returns an error instead of panicking when the counter can't advance,
+    i.e. when it reaches `IndexT::max_value()`. The counter stays where it is on error.
+
+    ```
+    use stable_id::{Exhausted, Sequence};
+
+    let mut s = Sequence::continue_from(254u8);
+    assert_eq!(s.next_value_checked(), Ok(254));
+    assert_eq!(s.next_value_checked(), Err(Exhausted));
+    assert_eq!(s.peek_value(), 255);
+    ```
+    */
+    pub fn next_value_checked(&mut self) -> Result<IndexT, Exhausted> {
+        if self.counter == IndexT::max_value() {
+            Err(Exhausted)
+        } else {
+            Ok(self.next_value())
+        }
+    }
+}
+
+impl<IndexT> Sequence<IndexT>
+where
+    IndexT: SequenceIndex + Maximum + CastUsize,
+{
+    /**
+    Issue `n` consecutive ids at once, i.e. the same ids as calling [`Self::next_value()`] `n` times.
+    Panic if the counter would go past `IndexT::max_value()`.
+
+    ```
+    use stable_id::Sequence;
+
+    let mut s: Sequence<u8> = Default::default();
+    assert_eq!(s.next_range(3), 0..3);
+    assert_eq!(s.next_value(), 3);
+    ```
+    */
+    pub fn next_range(&mut self, n: usize) -> Range<IndexT> {
+        let start = self.counter;
+        let end = start
+            .cast_to()
+            .checked_add(n)
+            .filter(|end| *end <= IndexT::max_value().cast_to())
+            .expect("ran out of ids");
+
+        self.counter = IndexT::cast_from(end);
+        start..self.counter
+    }
+}
+
+impl<IndexT> Sequence<IndexT>
+where
+    IndexT: SequenceIndex + Ord,
+{
+    /**
+    Remember the current state of the counter, so that it can be restored by [`Self::rollback_to()`].
+    */
+    pub fn checkpoint(&self) -> IndexT {
+        self.counter
+    }
+
+    /**
+    Restore the counter to a state returned by [`Self::checkpoint()`], so that ids issued since then will be issued again.
+    Panic if the checkpoint is ahead of the counter.
+
+    Be careful: rolling back is only safe if none of the ids issued after the checkpoint are still in use,
+    otherwise they will be handed out twice.
+
+    ```
+    use stable_id::Sequence;
+
+    let mut s: Sequence<u8> = Default::default();
+    assert_eq!(s.next_value(), 0);
+
+    let checkpoint = s.checkpoint();
+    assert_eq!(s.next_value(), 1);
+    assert_eq!(s.next_value(), 2);
+
+    // abort the tentative operation
+    s.rollback_to(checkpoint);
+    assert_eq!(s.next_value(), 1);
+    ```
+    */
+    pub fn rollback_to(&mut self, checkpoint: IndexT) {
+        assert!(
+            checkpoint <= self.counter,
+            "checkpoint is ahead of the sequence"
+        );
+        self.counter = checkpoint;
+    }
+}
+
+impl<IndexT> Sequence<IndexT>
+where
+    IndexT: EidsIndex + CastUsize,
+{
+    /**
+    Start recycling ids: the returned [`Eids`] has issued every id below the counter, with none of them freed yet.
+    See [`Eids::into_sequence()`] for the other way around.
+
+    ```
+    use stable_id::Sequence;
+
+    let mut seq: Sequence<u8> = Default::default();
+    let a = seq.next_value();
+
+    let mut entities = seq.into_eids();
+    entities.unclaim(a);
+    assert_eq!(entities.claim(), a);
+    assert_eq!(entities.claim(), 1);
+    ```
+    */
+    pub fn into_eids(self) -> Eids<IndexT> {
+        Eids::from_parts(Some(self.counter), iter::empty())
+    }
+}
+
+impl<IndexT> IdAllocator<IndexT> for Sequence<IndexT>
+where
+    IndexT: SequenceIndex,
+{
+    fn alloc(&mut self) -> IndexT {
+        self.next_value()
+    }
+
+    /// Ids are never recycled, so this does nothing.
+    fn free(&mut self, _id: IndexT) {}
+
+    fn peek_next(&self) -> IndexT {
+        self.peek_value()
+    }
+}
+
+impl Display for Exhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ran out of ids")
+    }
+}
+
+impl Error for Exhausted {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Exhausted, IdAllocator, Sequence};
+
+    #[test]
+    fn id_allocator() {
+        let mut seq: Sequence<u8> = Default::default();
+        let allocator: &mut dyn IdAllocator<u8> = &mut seq;
+
+        assert_eq!(allocator.alloc(), 0);
+        assert_eq!(allocator.alloc(), 1);
+        allocator.free(0);
+        assert_eq!(allocator.peek_next(), 2);
+        assert_eq!(allocator.alloc(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "checkpoint is ahead of the sequence")]
+    fn rollback_to_future() {
+        let mut seq: Sequence<u8> = Default::default();
+        seq.rollback_to(1);
+    }
+
+    #[test]
+    fn next_value_checked() {
+        let mut seq: Sequence<u8> = Default::default();
+        (0..u8::MAX).for_each(|i| assert_eq!(seq.next_value_checked(), Ok(i)));
+
+        // stays exhausted without moving the counter
+        assert_eq!(seq.next_value_checked(), Err(Exhausted));
+        assert_eq!(seq.next_value_checked(), Err(Exhausted));
+        assert_eq!(seq.peek_value(), u8::MAX);
+    }
+
+    #[test]
+    fn next_range() {
+        let mut seq: Sequence<u8> = Default::default();
+        assert_eq!(seq.next_value(), 0);
+
+        let range = seq.next_range(10);
+        assert_eq!(range.len(), 10);
+        assert_eq!(range, 1..11);
+        assert_eq!(seq.next_value(), 11);
+
+        assert!(seq.next_range(0).is_empty());
+        assert_eq!(seq.next_range(u8::MAX as usize - 12), 12..u8::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "ran out of ids")]
+    fn next_range_overflow() {
+        let mut seq = Sequence::continue_from(250u8);
+        let _ = seq.next_range(6);
+    }
+
+    #[test]
+    fn wrapping_next_value() {
+        let mut seq: Sequence<u8> = Default::default();
+        (0..=u8::MAX).for_each(|i| assert_eq!(seq.wrapping_next_value(), i));
+        (0..10).for_each(|i| assert_eq!(seq.wrapping_next_value(), i));
+        assert_eq!(seq.next_value(), 10);
+    }
+
+    #[test]
+    fn from_raw() {
+        let mut seq = Sequence::<u8>::from_raw(42);
+        assert_eq!(seq.peek_value(), 42);
+        assert_eq!(seq.next_value(), 42);
+        assert_eq!(Sequence::from_raw(seq.peek_value()).next_value(), 43);
+    }
+}
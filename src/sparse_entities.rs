@@ -1,16 +1,31 @@
 use std::{
-    hash::Hash,
+    collections::HashMap,
+    hash::BuildHasher,
+    iter::FusedIterator,
     ops::{Index, IndexMut},
 };
 
-use stable_id_traits::Successor;
-
-use super::SparseEntities;
+use crate::{
+    hash_map, Entities, EntitiesIndex, IntoIter, SparseEntities, SparseEntitiesIndex,
+    StableCollection,
+};
 
-impl<IndexT, DataT> SparseEntities<IndexT, DataT>
+impl<IndexT, DataT, S> SparseEntities<IndexT, DataT, S>
 where
-    IndexT: Successor + Clone + Copy + Hash + Eq + Default,
+    IndexT: SparseEntitiesIndex,
+    S: BuildHasher,
 {
+    /**
+    Creates an empty collection that hashes the ids with `hasher`, e.g. a fixed-seed hasher
+    for a reproducible iteration order.
+    */
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            data: hash_map::HashMap::with_hasher(hasher),
+            seq: Default::default(),
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -19,6 +34,25 @@ where
         self.data.is_empty()
     }
 
+    /**
+    Removes all items while keeping the allocated memory for reuse.
+    The id sequence carries on, so ids issued afterward never collide with ids issued before.
+    */
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /**
+    Removes all items and restart the ids from zero.
+
+    **Warning**: unlike [`Self::clear()`], ids issued before will be issued again, so any handle that outlives this call
+    may silently refer to a new, unrelated item.
+    */
+    pub fn clear_and_reset(&mut self) {
+        self.clear();
+        self.seq = Default::default();
+    }
+
     pub fn get(&self, index: IndexT) -> Option<&DataT> {
         self.data.get(&index)
     }
@@ -27,6 +61,40 @@ where
         self.data.get_mut(&index)
     }
 
+    /**
+    Borrow several items mutably at once. Return `None` if any of the ids is missing or if an id is repeated.
+
+    ```
+    use stable_id::SparseEntities;
+
+    let mut entities: SparseEntities<u8, i32> = Default::default();
+    let a = entities.alloc(1);
+    let b = entities.alloc(2);
+
+    let [x, y] = entities.get_disjoint_mut([a, b]).unwrap();
+    std::mem::swap(x, y);
+    assert_eq!(entities[a], 2);
+
+    assert!(entities.get_disjoint_mut([a, a]).is_none());
+    ```
+    */
+    pub fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        ids: [IndexT; N],
+    ) -> Option<[&mut DataT; N]> {
+        // HashMap::get_disjoint_mut() panics on repeated keys, and is the reason for the rust-version of 1.86+
+        let has_duplicate = ids.iter().enumerate().any(|(i, id)| ids[..i].contains(id));
+        if has_duplicate {
+            return None;
+        }
+
+        let result = self.data.get_disjoint_mut(ids.each_ref());
+        if result.iter().any(Option::is_none) {
+            return None;
+        }
+        Some(result.map(|data| data.expect("checked above")))
+    }
+
     /** Panic if index is invalid */
     pub fn remove(&mut self, index: IndexT) -> DataT {
         self.data.remove(&index).expect("id is not value")
@@ -50,21 +118,71 @@ where
             .iter_mut()
             .map(|(virtual_id, data)| (*virtual_id, data))
     }
+
+    /** Collect references of all items into a std [`HashMap`]. */
+    pub fn to_hashmap(&self) -> HashMap<IndexT, &DataT> {
+        self.iter().collect()
+    }
+
+    /** Consuming version of [`Self::to_hashmap()`]. */
+    pub fn into_hashmap(self) -> HashMap<IndexT, DataT> {
+        self.data.into_iter().collect()
+    }
+}
+
+impl<IndexT, DataT, S> SparseEntities<IndexT, DataT, S>
+where
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
+{
+    /**
+    Move every items out of an [`Entities`] while keeping their ids, i.e. the raw values of their [`crate::Handle`]s.
+    Newly allocated ids continue from where `entities` left off, so they don't collide with ids issued by `entities`.
+    */
+    pub fn from_entities(entities: Entities<IndexT, DataT, S>) -> Self
+    where
+        S: Default,
+    {
+        let seq = entities.seq.clone();
+        let data = entities
+            .into_iter_with_id()
+            .map(|(handle, data)| (handle.raw(), data))
+            .collect();
+
+        Self { data, seq }
+    }
 }
 
-impl<IndexT, DataT> IntoIterator for SparseEntities<IndexT, DataT>
+impl<IndexT, DataT, S> IntoIterator for SparseEntities<IndexT, DataT, S>
 where
-    IndexT: Successor + Clone + Copy + Default + Hash + Eq,
+    IndexT: SparseEntitiesIndex,
+    S: BuildHasher,
 {
     type Item = (IndexT, DataT);
 
-    type IntoIter = std::collections::hash_map::IntoIter<IndexT, DataT>;
+    type IntoIter = IntoIter<IndexT, DataT>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.data.into_iter()
+        IntoIter(self.data.into_iter())
+    }
+}
+
+impl<IndexT, DataT> Iterator for IntoIter<IndexT, DataT> {
+    type Item = (IndexT, DataT);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
 }
 
+impl<IndexT, DataT> ExactSizeIterator for IntoIter<IndexT, DataT> {}
+
+impl<IndexT, DataT> FusedIterator for IntoIter<IndexT, DataT> {}
+
 impl<IndexT, DataT> Default for SparseEntities<IndexT, DataT>
 where
     IndexT: Default,
@@ -77,9 +195,43 @@ where
     }
 }
 
-impl<IndexT, DataT> Index<IndexT> for SparseEntities<IndexT, DataT>
+impl<IndexT, DataT, S> StableCollection<IndexT, DataT> for SparseEntities<IndexT, DataT, S>
 where
-    IndexT: Successor + Clone + Copy + Hash + Eq + Default,
+    IndexT: SparseEntitiesIndex,
+    S: BuildHasher,
+{
+    fn alloc(&mut self, data: DataT) -> IndexT {
+        SparseEntities::alloc(self, data)
+    }
+
+    fn get(&self, index: IndexT) -> Option<&DataT> {
+        SparseEntities::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
+        SparseEntities::get_mut(self, index)
+    }
+
+    fn remove(&mut self, index: IndexT) -> Option<DataT> {
+        self.data.remove(&index)
+    }
+
+    fn len(&self) -> usize {
+        SparseEntities::len(self)
+    }
+
+    fn iter_with_id<'a>(&'a self) -> impl Iterator<Item = (IndexT, &'a DataT)>
+    where
+        DataT: 'a,
+    {
+        SparseEntities::iter(self)
+    }
+}
+
+impl<IndexT, DataT, S> Index<IndexT> for SparseEntities<IndexT, DataT, S>
+where
+    IndexT: SparseEntitiesIndex,
+    S: BuildHasher,
 {
     type Output = DataT;
 
@@ -88,9 +240,10 @@ where
     }
 }
 
-impl<IndexT, DataT> IndexMut<IndexT> for SparseEntities<IndexT, DataT>
+impl<IndexT, DataT, S> IndexMut<IndexT> for SparseEntities<IndexT, DataT, S>
 where
-    IndexT: Successor + Clone + Copy + Hash + Eq + Default,
+    IndexT: SparseEntitiesIndex,
+    S: BuildHasher,
 {
     fn index_mut(&mut self, index: IndexT) -> &mut Self::Output {
         self.get_mut(index).expect("element not exist")
@@ -154,4 +307,84 @@ mod tests {
         assert!(entities.is_empty());
         check_all(&entities);
     }
+
+    #[test]
+    fn to_hashmap() {
+        let mut entities = SparseEntities::default();
+        ["a", "b", "c"].into_iter().for_each(|c| {
+            entities.alloc(c.to_owned());
+        });
+        entities.remove(1u8);
+
+        let expected = HashMap::from([(0, "a".to_owned()), (2, "c".to_owned())]);
+
+        assert_eq!(
+            entities.to_hashmap(),
+            expected.iter().map(|(id, data)| (*id, data)).collect()
+        );
+        assert_eq!(entities.into_hashmap(), expected);
+    }
+
+    #[test]
+    fn clear() {
+        let mut entities: SparseEntities<u8, u8> = SparseEntities::default();
+        (0..100).for_each(|i| {
+            entities.alloc(i);
+        });
+        let capacity = entities.data.capacity();
+
+        entities.clear();
+        assert!(entities.is_empty());
+        assert_eq!(entities.data.capacity(), capacity);
+        assert_eq!(entities.alloc(1), 100);
+    }
+
+    #[test]
+    fn clear_and_reset() {
+        let mut entities: SparseEntities<u8, u8> = SparseEntities::default();
+        (0..100).for_each(|i| {
+            entities.alloc(i);
+        });
+
+        entities.clear_and_reset();
+        assert!(entities.is_empty());
+        assert_eq!(entities.alloc(1), 0);
+        assert_eq!(entities[0], 1);
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut entities: SparseEntities<u16, char> = Default::default();
+        let ids: Vec<_> = "abc".chars().map(|c| entities.alloc(c)).collect();
+        entities.remove(ids[1]);
+
+        let mut iter = entities.into_iter();
+        assert_eq!(iter.len(), 2);
+        let mut items: Vec<_> = iter.by_ref().collect();
+        items.sort_unstable();
+        assert_eq!(items, [(ids[0], 'a'), (ids[2], 'c')]);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn get_disjoint_mut() {
+        let mut entities: SparseEntities<u16, u32> = Default::default();
+        let ids: Vec<_> = (0..5).map(|i| entities.alloc(i)).collect();
+        entities.remove(ids[3]);
+
+        let [x, y, z] = entities.get_disjoint_mut([ids[4], ids[0], ids[2]]).unwrap();
+        *x += 10;
+        *y += 20;
+        *z += 30;
+        assert_eq!(entities[ids[4]], 14);
+        assert_eq!(entities[ids[0]], 20);
+        assert_eq!(entities[ids[2]], 32);
+
+        assert!(entities.get_disjoint_mut([ids[0], ids[3]]).is_none());
+        assert!(entities.get_disjoint_mut([ids[1], 100]).is_none());
+        assert!(entities
+            .get_disjoint_mut([ids[1], ids[2], ids[1]])
+            .is_none());
+        assert_eq!(entities.get_disjoint_mut::<0>([]), Some([]));
+    }
 }
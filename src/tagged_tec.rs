@@ -0,0 +1,164 @@
+use std::mem;
+
+use stable_id_traits::Maximum;
+
+use crate::{TaggedTec, Tec, TecIndex};
+
+impl<IndexT, DataT, TagT> Default for TaggedTec<IndexT, DataT, TagT>
+where
+    IndexT: Maximum,
+{
+    fn default() -> Self {
+        Self {
+            tec: Default::default(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl<IndexT, DataT, TagT> TaggedTec<IndexT, DataT, TagT>
+where
+    IndexT: TecIndex,
+    TagT: Default,
+{
+    /// Read-only access to the underlying [`Tec`].
+    pub fn as_tec(&self) -> &Tec<IndexT, DataT> {
+        &self.tec
+    }
+
+    /// Number of items in this data structure.
+    pub fn len(&self) -> usize {
+        self.tec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tec.is_empty()
+    }
+
+    /// Same as [`Tec::alloc()`], with the default tag.
+    pub fn alloc(&mut self, data: DataT) -> IndexT {
+        self.alloc_with_tag(data, Default::default())
+    }
+
+    /// Same as [`Tec::alloc()`], with the given `tag` attached to the slot.
+    pub fn alloc_with_tag(&mut self, data: DataT, tag: TagT) -> IndexT {
+        let id = self.tec.alloc(data);
+        let index = id.cast_to();
+
+        if index == self.tags.len() {
+            self.tags.push(tag);
+        } else {
+            self.tags[index] = tag;
+        }
+
+        debug_assert_eq!(self.tags.len(), self.tec.capacity());
+
+        id
+    }
+
+    /** Same as [`Tec::remove()`], but also gives back the tag. Panic if index is invalid. */
+    pub fn remove(&mut self, index: IndexT) -> (DataT, TagT) {
+        let data = self.tec.remove(index);
+        let tag = mem::take(&mut self.tags[index.cast_to()]);
+        (data, tag)
+    }
+
+    pub fn get(&self, index: IndexT) -> Option<&DataT> {
+        self.tec.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
+        self.tec.get_mut(index)
+    }
+
+    /// The tag of a living item.
+    pub fn get_tag(&self, index: IndexT) -> Option<&TagT> {
+        self.tec.get(index).map(|_| &self.tags[index.cast_to()])
+    }
+
+    /// Replace the tag of a living item and return the old one, or return `None` if there is no such item.
+    pub fn set_tag(&mut self, index: IndexT, tag: TagT) -> Option<TagT> {
+        self.tec
+            .get(index)
+            .map(|_| mem::replace(&mut self.tags[index.cast_to()], tag))
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &DataT> {
+        self.tec.iter()
+    }
+
+    pub fn iter_with_id(&self) -> impl DoubleEndedIterator<Item = (IndexT, &DataT)> {
+        self.tec.iter_with_id()
+    }
+
+    /// Same as [`Self::iter_with_id()`], along with the tags.
+    pub fn iter_with_tag(&self) -> impl DoubleEndedIterator<Item = (IndexT, &DataT, &TagT)> {
+        self.tec
+            .iter_positions()
+            .map(|(position, id, data)| (id, data, &self.tags[position]))
+    }
+
+    /**
+    Same as [`Tec::coalesce()`], and the tags are moved along with their items.
+    */
+    pub fn coalesce<F>(&mut self, mut f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        let tags = &mut self.tags;
+        self.tec.coalesce(|old_id, new_id| {
+            // the target slot is dead, so its tag is just a default placeholder
+            tags.swap(old_id.cast_to(), new_id.cast_to());
+            f(old_id, new_id);
+        });
+        self.tags.truncate(self.tec.capacity());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaggedTec;
+
+    #[test]
+    fn alloc_and_remove() {
+        let mut storage: TaggedTec<u8, char, u8> = Default::default();
+        let a = storage.alloc_with_tag('a', 1);
+        let b = storage.alloc('b');
+
+        assert_eq!(storage.get_tag(a), Some(&1));
+        assert_eq!(storage.get_tag(b), Some(&0));
+
+        assert_eq!(storage.remove(a), ('a', 1));
+        assert_eq!(storage.get_tag(a), None);
+        assert_eq!(storage.set_tag(a, 5), None);
+
+        // the reused slot doesn't inherit the tag of the removed item
+        let c = storage.alloc('c');
+        assert_eq!(c, a);
+        assert_eq!(storage.get_tag(c), Some(&0));
+        assert_eq!(storage.set_tag(c, 2), Some(0));
+
+        assert!(storage.iter_with_tag().eq([(c, &'c', &2), (b, &'b', &0)]));
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut storage: TaggedTec<u8, u8, u8> = Default::default();
+        (0..100).for_each(|i| {
+            storage.alloc_with_tag(i, i.wrapping_mul(7));
+        });
+        (0..100).filter(|i| i % 3 == 0).for_each(|i| {
+            storage.remove(i);
+        });
+
+        let mut moves = 0;
+        storage.coalesce(|_, _| moves += 1);
+
+        assert!(moves > 0);
+        assert!(storage.as_tec().is_compact());
+        assert_eq!(storage.tags.len(), storage.len());
+        assert!(storage
+            .iter_with_tag()
+            .all(|(_, data, tag)| *tag == data.wrapping_mul(7)));
+    }
+}
@@ -0,0 +1,45 @@
+/// Wraps an iterator over the living slots of a [`crate::Tec`] to report an exact `size_hint`, since the `Tec`
+/// already knows how many items are alive, whereas the underlying `filter_map` can only give a range.
+pub(crate) struct ExactLen<I> {
+    inner: I,
+    remaining: usize,
+}
+
+impl<I> ExactLen<I> {
+    pub(crate) fn new(inner: I, len: usize) -> Self {
+        Self {
+            inner,
+            remaining: len,
+        }
+    }
+}
+
+impl<I> Iterator for ExactLen<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<I> DoubleEndedIterator for ExactLen<I>
+where
+    I: DoubleEndedIterator,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next_back()?;
+        self.remaining -= 1;
+        Some(item)
+    }
+}
+
+impl<I> ExactSizeIterator for ExactLen<I> where I: Iterator {}
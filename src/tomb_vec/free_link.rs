@@ -0,0 +1,60 @@
+/*!
+Links of the free list that chains the dead slots of a [`crate::Tec`] together.
+
+By default, a link is just an `IndexT`, with `IndexT::max_value()` as the sentinel marking the end of the list.
+That means the last id can never be issued.
+With the `full-range` feature, a link is an `Option<IndexT>` instead, so that every id up to and including
+`IndexT::max_value()` can be issued, at the cost of a bigger link.
+*/
+
+use stable_id_traits::{CastUsize, Maximum};
+
+#[cfg(not(feature = "full-range"))]
+pub(crate) type FreeLink<IndexT> = IndexT;
+
+#[cfg(feature = "full-range")]
+pub(crate) type FreeLink<IndexT> = Option<IndexT>;
+
+/// The link that marks the end of the free list.
+#[cfg(not(feature = "full-range"))]
+pub(crate) fn end<IndexT: Maximum>() -> FreeLink<IndexT> {
+    IndexT::max_value()
+}
+
+#[cfg(feature = "full-range")]
+pub(crate) fn end<IndexT: Maximum>() -> FreeLink<IndexT> {
+    None
+}
+
+/// The link pointing to the dead slot at `index`.
+#[cfg(not(feature = "full-range"))]
+pub(crate) fn to<IndexT>(index: IndexT) -> FreeLink<IndexT> {
+    index
+}
+
+#[cfg(feature = "full-range")]
+pub(crate) fn to<IndexT>(index: IndexT) -> FreeLink<IndexT> {
+    Some(index)
+}
+
+/// The index of the dead slot that `link` points to, or `None` at the end of the free list.
+#[cfg(not(feature = "full-range"))]
+pub(crate) fn target<IndexT: Maximum + Eq>(link: FreeLink<IndexT>) -> Option<IndexT> {
+    (link != IndexT::max_value()).then_some(link)
+}
+
+#[cfg(feature = "full-range")]
+pub(crate) fn target<IndexT: Maximum + Eq>(link: FreeLink<IndexT>) -> Option<IndexT> {
+    link
+}
+
+/// The maximum number of slots, i.e. all valid ids are smaller than this.
+#[cfg(not(feature = "full-range"))]
+pub(crate) fn max_slots<IndexT: Maximum + CastUsize>() -> usize {
+    IndexT::max_value().cast_to()
+}
+
+#[cfg(feature = "full-range")]
+pub(crate) fn max_slots<IndexT: Maximum + CastUsize>() -> usize {
+    IndexT::max_value().cast_to().saturating_add(1)
+}
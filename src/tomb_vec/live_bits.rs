@@ -0,0 +1,233 @@
+/*!
+A bitset of the living slots of a [`crate::Tec`], maintained under the `fast-iter` feature, so that iterating
+a mostly-dead storage skips the dead slots a word (64 slots) at a time instead of visiting them one by one.
+*/
+
+use std::ops::Range;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LiveBits {
+    words: Vec<u64>,
+}
+
+impl LiveBits {
+    /// Every slot in `0..len` is alive.
+    pub(crate) fn compact(len: usize) -> Self {
+        let mut words = vec![u64::MAX; len / WORD_BITS];
+        if !len.is_multiple_of(WORD_BITS) {
+            words.push((1 << (len % WORD_BITS)) - 1);
+        }
+        Self { words }
+    }
+
+    pub(crate) fn insert(&mut self, position: usize) {
+        let word_index = position / WORD_BITS;
+        if word_index >= self.words.len() {
+            self.words.resize(word_index + 1, 0);
+        }
+        self.words[word_index] |= 1 << (position % WORD_BITS);
+    }
+
+    pub(crate) fn remove(&mut self, position: usize) {
+        if let Some(word) = self.words.get_mut(position / WORD_BITS) {
+            *word &= !(1 << (position % WORD_BITS));
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Positions of the living slots in ascending order.
+    pub(crate) fn positions(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.positions_in(0..usize::MAX)
+    }
+
+    /// Same as [`Self::positions()`], but only those within `range`.
+    pub(crate) fn positions_in(
+        &self,
+        range: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        let end = range.end.min(self.words.len() * WORD_BITS);
+        let start = range.start.min(end);
+        let first_word = start / WORD_BITS;
+
+        self.words[first_word..end.div_ceil(WORD_BITS)]
+            .iter()
+            .enumerate()
+            .map(move |(i, word)| {
+                let base = (first_word + i) * WORD_BITS;
+                let mut word = *word;
+                if base < start {
+                    word &= u64::MAX << (start - base);
+                }
+                if end - base < WORD_BITS {
+                    word &= (1 << (end - base)) - 1;
+                }
+                Bits { word, base }
+            })
+            .filter(|bits| bits.word != 0)
+            .flatten()
+    }
+
+    /// Consuming version of [`Self::positions()`].
+    pub(crate) fn into_positions(self) -> impl DoubleEndedIterator<Item = usize> {
+        self.words
+            .into_iter()
+            .enumerate()
+            .filter(|(_, word)| *word != 0)
+            .flat_map(|(word_index, word)| Bits {
+                word,
+                base: word_index * WORD_BITS,
+            })
+    }
+}
+
+/**
+Pick the slots at `positions` out of `slots`, whose first slot is at position `offset`, skipping the others with
+[`Iterator::nth()`], which is O(1) for slice iterators. `positions` must be ascending and within `slots`.
+*/
+pub(crate) struct LiveSlots<S, P> {
+    slots: S,
+    positions: P,
+    front: usize,
+    back: usize,
+}
+
+impl<S, P> LiveSlots<S, P>
+where
+    S: ExactSizeIterator,
+{
+    pub(crate) fn new(slots: S, positions: P, offset: usize) -> Self {
+        let back = offset + slots.len();
+        Self {
+            slots,
+            positions,
+            front: offset,
+            back,
+        }
+    }
+}
+
+impl<S, P> Iterator for LiveSlots<S, P>
+where
+    S: Iterator,
+    P: Iterator<Item = usize>,
+{
+    type Item = (usize, S::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let position = self.positions.next()?;
+        let slot = self.slots.nth(position - self.front)?;
+        self.front = position + 1;
+        Some((position, slot))
+    }
+}
+
+impl<S, P> DoubleEndedIterator for LiveSlots<S, P>
+where
+    S: DoubleEndedIterator,
+    P: DoubleEndedIterator<Item = usize>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let position = self.positions.next_back()?;
+        let slot = self.slots.nth_back(self.back - 1 - position)?;
+        self.back = position;
+        Some((position, slot))
+    }
+}
+
+/// The set bits of a single word.
+struct Bits {
+    word: u64,
+    base: usize,
+}
+
+impl Iterator for Bits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.word == 0 {
+            return None;
+        }
+
+        let bit = self.word.trailing_zeros();
+        self.word &= self.word - 1;
+        Some(self.base + bit as usize)
+    }
+}
+
+impl DoubleEndedIterator for Bits {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.word == 0 {
+            return None;
+        }
+
+        let bit = u64::BITS - 1 - self.word.leading_zeros();
+        self.word &= !(1 << bit);
+        Some(self.base + bit as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LiveBits, LiveSlots};
+
+    #[test]
+    fn positions() {
+        let mut bits = LiveBits::default();
+        [0, 63, 64, 200, 5].into_iter().for_each(|i| bits.insert(i));
+        bits.remove(5);
+        bits.remove(1000);
+
+        assert!(bits.positions().eq([0, 63, 64, 200]));
+        assert!(bits.positions().rev().eq([200, 64, 63, 0]));
+
+        let mut positions = bits.positions();
+        assert_eq!(positions.next(), Some(0));
+        assert_eq!(positions.next_back(), Some(200));
+        assert!(positions.eq([63, 64]));
+
+        bits.clear();
+        assert_eq!(bits.positions().next(), None);
+    }
+
+    #[test]
+    fn positions_in() {
+        let mut bits = LiveBits::default();
+        [0, 63, 64, 127, 128, 200]
+            .into_iter()
+            .for_each(|i| bits.insert(i));
+
+        assert!(bits.positions_in(0..usize::MAX).eq(bits.positions()));
+        assert!(bits.positions_in(1..128).eq([63, 64, 127]));
+        assert!(bits.positions_in(64..64).eq([]));
+        assert!(bits.positions_in(64..201).rev().eq([200, 128, 127, 64]));
+        assert!(bits.positions_in(201..1000).eq([]));
+        assert!(bits.clone().into_positions().eq(bits.positions()));
+    }
+
+    #[test]
+    fn live_slots() {
+        let mut bits = LiveBits::default();
+        [10, 11, 70, 99].into_iter().for_each(|i| bits.insert(i));
+        let slots: Vec<_> = (10..100).collect();
+
+        let mut live = LiveSlots::new(slots.iter(), bits.positions(), 10);
+        assert_eq!(live.next(), Some((10, &10)));
+        assert_eq!(live.next_back(), Some((99, &99)));
+        assert_eq!(live.next_back(), Some((70, &70)));
+        assert_eq!(live.next(), Some((11, &11)));
+        assert_eq!(live.next(), None);
+        assert_eq!(live.next_back(), None);
+    }
+
+    #[test]
+    fn compact() {
+        assert!(LiveBits::compact(0).positions().eq(0..0));
+        assert!(LiveBits::compact(64).positions().eq(0..64));
+        assert!(LiveBits::compact(130).positions().eq(0..130));
+    }
+}
@@ -1,18 +1,42 @@
+mod exact_len;
+pub(crate) mod free_link;
+#[cfg(feature = "fast-iter")]
+pub(crate) mod live_bits;
 mod tomb_vec_tests;
+mod view;
 
-use std::fmt::Debug;
+use std::error::Error;
+use std::fmt::{Debug, Display};
 
-use std::cmp::Reverse;
-use std::collections::BinaryHeap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Mutex, OnceLock, PoisonError};
 
 use std::{
     mem,
-    ops::{Index, IndexMut},
+    ops::{Index, IndexMut, Range},
 };
 
+#[cfg(feature = "parallel")]
+use std::{num::NonZeroUsize, thread};
+
 use stable_id_traits::{CastUsize, Maximum};
 
-use crate::{Slot, Tec};
+use crate::{
+    CapacityError, CoalesceStats, IndexError, OverlapError, ReinsertError, SealedError, Slot,
+    StableCollection, Tec, TecEvent, TecIndex, TecMutView, TecObserver, TecView,
+};
+
+use self::exact_len::ExactLen;
+
+use self::free_link::FreeLink;
+
+/// Observers are only reachable through `&mut Tec`, so the mutex is never contended and never poisoned.
+fn call_observer<IndexT>(observer: &mut Mutex<Box<TecObserver<IndexT>>>, event: TecEvent<IndexT>) {
+    let observer = observer.get_mut().unwrap_or_else(PoisonError::into_inner);
+    observer(event);
+}
 
 impl<IndexT, DataT> Default for Tec<IndexT, DataT>
 where
@@ -21,27 +45,168 @@ where
     fn default() -> Self {
         Self {
             vec: Default::default(),
-            next_free: Maximum::max_value(),
+            next_free: free_link::end(),
             count: 0,
+            total_allocated: 0,
+            observer: None,
+            sealed: false,
+            live_ids: OnceLock::new(),
+            #[cfg(feature = "debug-tracking")]
+            generations: Vec::new(),
+            #[cfg(feature = "fast-iter")]
+            live: Default::default(),
         }
     }
 }
 
 impl<IndexT, DataT> Tec<IndexT, DataT>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum,
+    IndexT: TecIndex,
 {
     fn set_sentinal(&mut self) {
-        self.next_free = Maximum::max_value();
+        self.next_free = free_link::end();
     }
 
-    fn check_free_link_invariant(&self, link: IndexT) -> bool {
-        let n = link.cast_to();
-        let m = IndexT::max_value().cast_to();
-
+    fn check_free_link_invariant(&self, link: FreeLink<IndexT>) -> bool {
         // either the free list link is pointing to a valid spot in memory
         // or it's pointing to the sentinal
-        n <= self.capacity() || n == m
+        match free_link::target(link) {
+            Some(n) => n.cast_to() <= self.capacity(),
+            None => true,
+        }
+    }
+
+    fn invalidate_live_ids(&mut self) {
+        self.live_ids.take();
+    }
+
+    fn notify(&mut self, event: TecEvent<IndexT>) {
+        if let Some(observer) = &mut self.observer {
+            call_observer(observer, event);
+        }
+    }
+
+    /// The item at `position` is gone, either removed or relocated, so any id that still points to it is stale.
+    #[cfg(feature = "debug-tracking")]
+    fn retire(&mut self, position: usize) {
+        if self.generations.len() <= position {
+            self.generations.resize(position + 1, 0);
+        }
+        self.generations[position] += 1;
+    }
+
+    #[cfg(feature = "debug-tracking")]
+    fn generation_by_usize(&self, position: usize) -> u32 {
+        self.generations.get(position).copied().unwrap_or(0)
+    }
+
+    /**
+    The number of times the slot at `index` has lost its item, either by removal or by relocation (e.g. [`Self::coalesce()`]).
+    Remember the generation along with the id to tell whether the id has gone stale, see [`Self::get_checked()`];
+    this is the only way to catch a reused slot, since the other accessors can't tell the new item from the old one.
+    Only available with the `debug-tracking` feature.
+    */
+    #[cfg(feature = "debug-tracking")]
+    pub fn generation(&self, index: IndexT) -> u32 {
+        self.generation_by_usize(index.cast_to())
+    }
+
+    /**
+    Same as [`Self::get()`], but panic if the slot has lost its item since `generation` was taken from
+    [`Self::generation()`], i.e. `index` is stale and may refer to an unrelated item.
+    Only available with the `debug-tracking` feature.
+    */
+    #[cfg(feature = "debug-tracking")]
+    pub fn get_checked(&self, index: IndexT, generation: u32) -> Option<&DataT> {
+        let position = index.cast_to();
+        let current = self.generation_by_usize(position);
+        assert!(
+            current == generation,
+            "use after remove: the slot at {position} is at generation {current}, expected {generation}"
+        );
+        self.get_by_usize(position)
+    }
+
+    /**
+    Register an observer that gets notified whenever a slot is allocated, removed, or relocated by [`Self::coalesce()`].
+    This replaces the previously registered observer, if any. There is no overhead when no observer is registered.
+
+    Note: observers are not carried over by [`Clone`].
+
+    ```
+    use std::sync::{Arc, Mutex};
+    use stable_id::{Tec, TecEvent};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut tec: Tec<u8, char> = Default::default();
+
+    let events_ref = events.clone();
+    tec.on_change(Box::new(move |event| events_ref.lock().unwrap().push(event)));
+
+    let id = tec.alloc('a');
+    tec.remove(id);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [TecEvent::Allocated(0), TecEvent::Removed(0)]
+    );
+    ```
+    */
+    pub fn on_change(&mut self, f: Box<TecObserver<IndexT>>) {
+        self.observer = Some(Mutex::new(f));
+    }
+
+    /** Unregister the observer set by [`Self::on_change()`]. */
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    /**
+    Forbid allocating, removing, and relocating items until [`Self::unseal()`] is called, i.e. [`Self::alloc()`],
+    [`Self::remove()`], [`Self::clear()`], [`Self::coalesce()`] and its variants, and [`Self::sort_storage_by()`] will
+    panic, while [`Self::try_alloc()`] and [`Self::try_remove()`] return [`SealedError`].
+    Useful to catch logic errors that modify a snapshot that is supposed to be read-only.
+    Items can still be accessed mutably.
+
+    ```
+    use stable_id::{SealedError, Tec};
+
+    let mut tec: Tec<u8, char> = Default::default();
+    let id = tec.alloc('a');
+    tec.seal();
+
+    assert_eq!(tec.try_alloc('b'), Err(SealedError));
+    assert_eq!(tec.try_remove(id), Err(SealedError));
+
+    tec.unseal();
+    assert_eq!(tec.try_remove(id), Ok(Some('a')));
+    ```
+    */
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /** Reverse [`Self::seal()`]. */
+    pub fn unseal(&mut self) {
+        self.sealed = false;
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.sealed
+    }
+
+    fn assert_unsealed(&self) {
+        if let Err(err) = self.check_unsealed() {
+            panic!("{}", err);
+        }
+    }
+
+    fn check_unsealed(&self) -> Result<(), SealedError> {
+        if self.sealed {
+            Err(SealedError)
+        } else {
+            Ok(())
+        }
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
@@ -51,6 +216,33 @@ where
         }
     }
 
+    /**
+    Same as [`Self::with_capacity()`], but fails if `IndexT` can't address `capacity` items,
+    so that picking an index type that is too small is caught up front rather than when the storage fills up.
+
+    ```
+    use stable_id::{CapacityError, Tec};
+
+    assert!(Tec::<u16, usize>::with_capacity_checked(300).is_ok());
+
+    let CapacityError { requested, max } = Tec::<u8, usize>::with_capacity_checked(300).unwrap_err();
+    assert_eq!(requested, 300);
+    assert!(max < 300);
+    ```
+    */
+    pub fn with_capacity_checked(capacity: usize) -> Result<Self, CapacityError> {
+        let max = free_link::max_slots::<IndexT>();
+
+        if capacity > max {
+            Err(CapacityError {
+                requested: capacity,
+                max,
+            })
+        } else {
+            Ok(Self::with_capacity(capacity))
+        }
+    }
+
     /// Number of items in this data structure.
     pub fn len(&self) -> usize {
         debug_assert_eq!(
@@ -65,23 +257,89 @@ where
         self.len() == 0
     }
 
+    /// Number of items that have ever been allocated, including those that were removed since.
+    /// Unlike [`Self::len()`], this never goes down, not even by [`Self::clear()`].
+    pub fn total_allocated(&self) -> u64 {
+        self.total_allocated
+    }
+
+    /// Removes all items. The allocation of the underlying `vec` is kept for reuse, see [`Self::clear_and_shrink()`]
+    /// to release it as well.
     pub fn clear(&mut self) {
+        self.assert_unsealed();
+
+        if self.observer.is_some() {
+            let ids: Vec<_> = self.iter_with_id().map(|(id, _)| id).collect();
+            ids.into_iter()
+                .for_each(|id| self.notify(TecEvent::Removed(id)));
+        }
+
+        #[cfg(feature = "debug-tracking")]
+        {
+            let positions: Vec<_> = self
+                .iter_positions()
+                .map(|(position, ..)| position)
+                .collect();
+            positions
+                .into_iter()
+                .for_each(|position| self.retire(position));
+        }
+
         self.vec.clear();
+        #[cfg(feature = "fast-iter")]
+        self.live.clear();
+        self.invalidate_live_ids();
         self.count = 0;
         self.set_sentinal();
     }
 
+    /**
+    Same as [`Self::clear()`], but also releases the memory of the underlying `vec`,
+    i.e. [`Self::allocated_capacity()`] drops to 0.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    tec.alloc('a');
+
+    tec.clear();
+    assert!(tec.allocated_capacity() > 0);
+
+    tec.clear_and_shrink();
+    assert_eq!(tec.allocated_capacity(), 0);
+    ```
+    */
+    pub fn clear_and_shrink(&mut self) {
+        self.clear();
+        self.shrink_to_fit();
+    }
+
     /**
     Allocates an id from the given `data`.
-    Note: can store at most IndexT::max_value() - 1 elements, because
-    the next free node needs to be count + 1.
+    Note: can store at most IndexT::max_value() elements (i.e. ids `0..IndexT::max_value()`), because
+    the maximum value is used as the sentinal of the free list. Enable the `full-range` feature to store
+    one more element, at the cost of a bigger free list link in each slot.
     */
     pub fn alloc(&mut self, data: DataT) -> IndexT {
-        let original_free_index = self.next_free;
+        self.assert_unsealed();
+        self.alloc_unsealed(data)
+    }
+
+    /// Same as [`Self::alloc()`], but returns an error instead of panicking if the `Tec` is sealed, see [`Self::seal()`].
+    pub fn try_alloc(&mut self, data: DataT) -> Result<IndexT, SealedError> {
+        self.check_unsealed()?;
+        Ok(self.alloc_unsealed(data))
+    }
 
-        let next_slot = self.vec.get_mut(original_free_index.cast_to());
+    fn alloc_unsealed(&mut self, data: DataT) -> IndexT {
+        let next_slot = free_link::target(self.next_free).and_then(|original_free_index| {
+            self.vec
+                .get_mut(original_free_index.cast_to())
+                .map(|slot| (original_free_index, slot))
+        });
 
-        let result_index = if let Some(slot) = next_slot {
+        let result_index = if let Some((original_free_index, slot)) = next_slot {
             match slot {
                 Slot::Alive(..) => unimplemented!("next free slot is already occupied"),
                 Slot::Dead { next_free } => {
@@ -91,245 +349,1767 @@ where
             }
             original_free_index
         } else {
-            let result_index = self.capacity();
+            let result_index = self.push_alive(data);
+            self.set_sentinal();
+            result_index
+        };
 
-            assert!(
-                result_index < IndexT::max_value().cast_to(),
-                "exceed storage limit"
-            );
+        self.finish_alloc(result_index);
+        result_index
+    }
 
-            self.vec.push(Slot::Alive(data));
-            self.set_sentinal();
-            IndexT::cast_from(result_index)
+    /**
+    Same as [`Self::alloc()`], but also returns a reference to the stored data, so that it can be tweaked right away.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, Vec<char>> = Default::default();
+    let (id, data) = tec.alloc_mut(Vec::new());
+    data.push('a');
+    assert_eq!(tec[id], ['a']);
+    ```
+    */
+    pub fn alloc_mut(&mut self, data: DataT) -> (IndexT, &mut DataT) {
+        let index = self.alloc(data);
+
+        // the slot was just filled, so there's no need to go through get_mut()
+        match &mut self.vec[index.cast_to()] {
+            Slot::Alive(data) => (index, data),
+            Slot::Dead { .. } => unreachable!("newly allocated slot is dead"),
+        }
+    }
+
+    /**
+    Allocate every item of `iter` and return their ids in the same order, i.e. a bulk [`Self::alloc()`]:
+    the dead slots are filled first in the order of [`Self::free_list()`], and then the rest is appended.
+    Space is reserved up front like [`Extend::extend()`].
+
+    Panic right away if `iter` is known to hold more items than the ids left, see [`Self::remaining_capacity()`].
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcd".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(0);
+    tec.remove(2);
+
+    let ids = tec.fill_from("xyz".chars());
+    assert_eq!(ids, [2, 0, 4]);
+    assert!(tec.iter().eq(&['y', 'b', 'x', 'd', 'z']));
+    ```
+    */
+    pub fn fill_from<I>(&mut self, iter: I) -> Vec<IndexT>
+    where
+        I: IntoIterator<Item = DataT>,
+    {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        self.assert_room_for(lower);
+        self.reserve(lower.saturating_sub(self.dead_count()));
+
+        let mut ids = Vec::with_capacity(lower);
+        ids.extend(iter.map(|data| self.alloc(data)));
+        ids
+    }
+
+    /**
+    Same as [`Self::alloc()`], but always appends `data` after the last slot, even if there are dead slots to reuse.
+    So ids returned by this method keep increasing (until the next [`Self::coalesce()`] or [`Self::clear()`]),
+    at the cost of leaving the tombstones in place, which keep taking up memory.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    let a = tec.alloc('a');
+    tec.alloc('b');
+    tec.remove(a);
+
+    assert_eq!(tec.alloc_at_end('c'), 2);
+    assert_eq!(tec.dead_count(), 1);
+    assert_eq!(tec.alloc('d'), a); // the dead slot is still reused by alloc()
+    ```
+    */
+    pub fn alloc_at_end(&mut self, data: DataT) -> IndexT {
+        self.assert_unsealed();
+
+        // the free list is left as is, since the new slot is alive
+        let result_index = self.push_alive(data);
+
+        self.finish_alloc(result_index);
+        result_index
+    }
+
+    /**
+    Put `data` back at `index`, e.g. to undo a removal so that the item keeps its original id.
+    The slot must be dead, or `index` must be exactly [`Self::capacity()`] to append the item.
+    Takes linear time in the number of dead slots, since the slot has to be unlinked from the free list.
+
+    ```
+    use stable_id::{ReinsertError, Tec};
+
+    let mut tec: Tec<u8, char> = Default::default();
+    let a = tec.alloc('a');
+    tec.alloc('b');
+
+    let removed = tec.remove(a);
+    assert_eq!(tec.reinsert(a, removed), Ok(()));
+    assert_eq!(tec[a], 'a');
+
+    assert_eq!(tec.reinsert(a, 'c'), Err(ReinsertError::Occupied));
+    assert_eq!(tec.reinsert(3, 'c'), Err(ReinsertError::OutOfBounds));
+    assert_eq!(tec.reinsert(2, 'c'), Ok(()));
+    ```
+    */
+    pub fn reinsert(&mut self, index: IndexT, data: DataT) -> Result<(), ReinsertError> {
+        self.assert_unsealed();
+
+        let position = index.cast_to();
+        if position == self.capacity() && position < free_link::max_slots::<IndexT>() {
+            self.push_alive(data);
+            self.finish_alloc(index);
+            Ok(())
+        } else {
+            self.alloc_at_dead(index, data)
+        }
+    }
+
+    /**
+    Similar to [`Self::alloc()`], but stores `data` in the dead slot at `index` instead of the one at the head of
+    the free list, e.g. to keep related items close to each other in memory.
+    Takes linear time in the number of dead slots, since the slot has to be unlinked from the free list.
+
+    ```
+    use stable_id::{ReinsertError, Tec};
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcd".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(1);
+    tec.remove(2);
+
+    assert_eq!(tec.alloc_at_dead(1, 'x'), Ok(()));
+    assert_eq!(tec.alloc_at_dead(1, 'y'), Err(ReinsertError::Occupied));
+    assert_eq!(tec.alloc_at_dead(4, 'y'), Err(ReinsertError::OutOfBounds));
+    assert_eq!(tec.alloc('z'), 2);
+    ```
+    */
+    pub fn alloc_at_dead(&mut self, index: IndexT, data: DataT) -> Result<(), ReinsertError> {
+        self.assert_unsealed();
+
+        let position = index.cast_to();
+        match self.vec.get(position) {
+            Some(Slot::Dead { .. }) => {}
+            Some(Slot::Alive(_)) => return Err(ReinsertError::Occupied),
+            None => return Err(ReinsertError::OutOfBounds),
+        }
+
+        self.unlink_free(index);
+        self.vec[position] = Slot::Alive(data);
+
+        self.finish_alloc(index);
+        Ok(())
+    }
+
+    /// Remove the dead slot at `index` from the free list, by pointing whatever links to it to its successor.
+    fn unlink_free(&mut self, index: IndexT) {
+        let after = match self.vec[index.cast_to()] {
+            Slot::Dead { next_free } => next_free,
+            Slot::Alive(_) => unreachable!("unlinking a living slot"),
         };
+        let link = free_link::to(index);
+
+        if self.next_free == link {
+            self.next_free = after;
+            return;
+        }
+
+        let mut cursor = self.next_free;
+        while let Some(current) = free_link::target(cursor) {
+            match &mut self.vec[current.cast_to()] {
+                Slot::Dead { next_free } if *next_free == link => {
+                    *next_free = after;
+                    return;
+                }
+                Slot::Dead { next_free } => cursor = *next_free,
+                Slot::Alive(_) => unreachable!("free list points to a living slot"),
+            }
+        }
+
+        unreachable!("dead slot is missing from the free list");
+    }
+
+    fn push_alive(&mut self, data: DataT) -> IndexT {
+        let result_index = self.capacity();
+
+        assert!(
+            result_index < free_link::max_slots::<IndexT>(),
+            "exceed storage limit"
+        );
+
+        self.vec.push(Slot::Alive(data));
+        IndexT::cast_from(result_index)
+    }
 
+    fn finish_alloc(&mut self, index: IndexT) {
+        #[cfg(feature = "fast-iter")]
+        self.live.insert(index.cast_to());
         self.count += 1;
+        self.total_allocated += 1;
+        self.invalidate_live_ids();
 
         debug_assert!(self.check_consistency());
 
-        result_index
+        self.notify(TecEvent::Allocated(index));
+    }
+
+    /**
+    Same as [`Self::take()`], but returns an error instead of panicking if the `Tec` is sealed, see [`Self::seal()`].
+    */
+    pub fn try_remove(&mut self, index: IndexT) -> Result<Option<DataT>, SealedError> {
+        self.check_unsealed()?;
+        Ok(self.take(index))
     }
 
     /** Panic if index is invalid */
     pub fn remove(&mut self, index: IndexT) -> DataT {
+        self.assert_unsealed();
         assert!(!self.is_empty(), "removing an item from an empty container");
 
+        self.take(index).expect("removing a dead item")
+    }
+
+    /**
+    Similar to [`Self::remove()`], but returns `None` instead of panicking if `index` doesn't refer to a living item.
+    Still panics if the `Tec` is sealed, see [`Self::seal()`].
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    let id = tec.alloc('a');
+
+    assert_eq!(tec.take(id), Some('a'));
+    assert_eq!(tec.take(id), None);
+    assert_eq!(tec.take(123), None);
+    ```
+    */
+    pub fn take(&mut self, index: IndexT) -> Option<DataT> {
+        self.assert_unsealed();
+
         // invariants: the free index must be either
         //      - pointer some dead slot within the vec
         //      - or the end of the vector
 
-        // we're doing panic! over Option, so just do the bookkeeping here since we don't need to recover anything
+        let removal_candidate = self.vec.get_mut(index.cast_to())?;
+        if matches!(removal_candidate, Slot::Dead { .. }) {
+            return None;
+        }
+
+        // replace the candidate with a dead slot that links to the rest of the free list
+        let removed = mem::replace(
+            removal_candidate,
+            Slot::Dead {
+                next_free: self.next_free,
+            },
+        );
+        let data = match removed {
+            Slot::Alive(data) => data,
+            Slot::Dead { .. } => unreachable!("cannot unwrap a dead item"),
+        };
+
         self.count -= 1;
+        self.next_free = free_link::to(index);
+        self.invalidate_live_ids();
+        #[cfg(feature = "debug-tracking")]
+        self.retire(index.cast_to());
+        #[cfg(feature = "fast-iter")]
+        self.live.remove(index.cast_to());
+        self.notify(TecEvent::Removed(index));
+
+        Some(data)
+    }
 
-        let index_usize = index.cast_to();
-        let removal_candidate = &mut self.vec[index_usize];
+    /**
+    Allocate or remove items until there are exactly `new_len` of them, similar to [`Vec::resize_with()`].
+    When growing, new items are created by `f()` and fill the dead slots before being appended.
+    When shrinking, the items with the highest ids are removed, and then the trailing dead slots are dropped.
 
-        let data = match removal_candidate {
-            Slot::Alive(_) => {
-                // create a dead slot and then swap it with the candidate
-                let mut temp_dead_slot = Slot::Dead {
-                    next_free: self.next_free,
-                };
-                mem::swap(&mut temp_dead_slot, removal_candidate);
+    ```
+    use stable_id::Tec;
 
-                // the temporary slot now has the removed item
+    let mut tec: Tec<u8, usize> = Default::default();
+    tec.resize_with(5, Default::default);
+    tec.remove(1);
 
-                self.next_free = index;
+    tec.resize_with(6, || 1);
+    assert_eq!(tec.iter().copied().collect::<Vec<_>>(), [0, 1, 0, 0, 0, 1]);
 
-                match temp_dead_slot {
-                    Slot::Alive(data) => data,
-                    Slot::Dead { .. } => unreachable!("cannot unwrap a dead item"),
-                }
+    tec.resize_with(2, || unreachable!());
+    assert_eq!(tec.iter_with_id().collect::<Vec<_>>(), [(0, &0), (1, &1)]);
+    assert_eq!(tec.capacity(), 2);
+    ```
+    */
+    pub fn resize_with<F>(&mut self, new_len: usize, mut f: F)
+    where
+        F: FnMut() -> DataT,
+    {
+        let len = self.len();
+
+        if new_len >= len {
+            (len..new_len).for_each(|_| {
+                self.alloc(f());
+            });
+        } else {
+            let doomed: Vec<_> = self
+                .iter_with_id()
+                .rev()
+                .take(len - new_len)
+                .map(|(id, _)| id)
+                .collect();
+            doomed.into_iter().for_each(|id| {
+                self.remove(id);
+            });
+
+            self.truncate_dead_tail();
+        }
+
+        debug_assert_eq!(self.len(), new_len);
+    }
+
+    /// Drop the trailing dead slots, and then relink the remaining ones so that the smallest ids are reused first.
+    fn truncate_dead_tail(&mut self) {
+        let end = self
+            .vec
+            .iter()
+            .rposition(|slot| matches!(slot, Slot::Alive(_)))
+            .map_or(0, |last_alive| last_alive + 1);
+
+        if end == self.capacity() {
+            return;
+        }
+
+        self.vec.truncate(end);
+        self.relink_free_list();
+
+        debug_assert!(self.check_consistency());
+    }
+
+    /// Rebuild the free list from scratch, so that the smallest ids are reused first.
+    fn relink_free_list(&mut self) {
+        self.set_sentinal();
+
+        for (position, slot) in self.vec.iter_mut().enumerate().rev() {
+            if let Slot::Dead { next_free } = slot {
+                *next_free = self.next_free;
+                self.next_free = free_link::to(IndexT::cast_from(position));
+            }
+        }
+    }
+
+    /**
+    Move every item of `other` into `self` under the same ids, e.g. to merge storages that were filled in parallel
+    with pre-assigned, disjoint ranges of ids. The storage grows as needed, and the gaps become dead slots.
+    Nothing is moved if any id of `other` refers to a living item of `self`.
+    Afterward, the dead slots are reused smallest id first.
+
+    ```
+    use stable_id::{OverlapError, Tec};
+
+    let mut low: Tec<u8, char> = Default::default();
+    low.alloc('a');
+    low.alloc('b');
+
+    let mut high: Tec<u8, char> = Default::default();
+    high.resize_with(5, || 'x');
+    (0..4).for_each(|id| {
+        high.remove(id);
+    });
+
+    assert_eq!(low.extend_from_disjoint(high.clone()), Ok(()));
+    assert_eq!(low.iter_with_id().collect::<Vec<_>>(), [(0, &'a'), (1, &'b'), (4, &'x')]);
+    assert_eq!(low.extend_from_disjoint(high), Err(OverlapError { id: 4 }));
+    ```
+    */
+    pub fn extend_from_disjoint(&mut self, other: Self) -> Result<(), OverlapError<IndexT>> {
+        self.assert_unsealed();
+
+        if let Some((id, _)) = other
+            .iter_with_id()
+            .find(|(id, _)| self.get_by_usize(id.cast_to()).is_some())
+        {
+            return Err(OverlapError { id });
+        }
+
+        let added = other.len();
+        let mut ids = Vec::with_capacity(added);
+        for (id, data) in other.into_iter_with_id() {
+            let position = id.cast_to();
+            while self.vec.len() <= position {
+                // linked for real by relink_free_list() below
+                self.vec.push(Slot::Dead {
+                    next_free: free_link::end(),
+                });
+            }
+
+            self.vec[position] = Slot::Alive(data);
+            #[cfg(feature = "fast-iter")]
+            self.live.insert(position);
+            ids.push(id);
+        }
+
+        self.relink_free_list();
+        self.count += added;
+        self.total_allocated += added as u64;
+        self.invalidate_live_ids();
+
+        debug_assert!(self.check_consistency());
+
+        if self.observer.is_some() {
+            ids.into_iter()
+                .for_each(|id| self.notify(TecEvent::Allocated(id)));
+        }
+
+        Ok(())
+    }
+
+    /**
+    Exchange the data of two living items, while the ids stay where they are.
+    Panic if either of the ids doesn't refer to a living item.
+    */
+    pub fn swap(&mut self, a: IndexT, b: IndexT) {
+        assert!(
+            self.get(a).is_some() && self.get(b).is_some(),
+            "swapping a dead item"
+        );
+
+        // both slots are alive, so swapping the slots is the same as swapping the data
+        self.vec.swap(a.cast_to(), b.cast_to());
+    }
+
+    /**
+    Note: a stale id whose slot has since been reused silently yields the new item. With the `debug-tracking` feature,
+    [`Self::get_checked()`] catches that, given the generation stored along with the id.
+    */
+    pub fn get(&self, index: IndexT) -> Option<&DataT> {
+        self.get_by_usize(index.cast_to())
+    }
+
+    /// Mutable version of [`Self::get()`].
+    pub fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
+        self.get_mut_by_usize(index.cast_to())
+    }
+
+    /**
+    Same as [`Self::get()`], but tells apart ids that are out of bounds from those of removed items,
+    so that lookups can be propagated with `?`.
+
+    ```
+    use stable_id::{IndexError, Tec};
+
+    let mut tec: Tec<u8, char> = Default::default();
+    let a = tec.alloc('a');
+    let b = tec.alloc('b');
+    tec.remove(a);
+
+    assert_eq!(tec.at(b), Ok(&'b'));
+    assert_eq!(tec.at(a), Err(IndexError::Dead));
+    assert_eq!(tec.at(2), Err(IndexError::OutOfBounds));
+    ```
+    */
+    pub fn at(&self, index: IndexT) -> Result<&DataT, IndexError> {
+        match self.vec.get(index.cast_to()) {
+            Some(Slot::Alive(data)) => Ok(data),
+            Some(Slot::Dead { .. }) => Err(IndexError::Dead),
+            None => Err(IndexError::OutOfBounds),
+        }
+    }
+
+    /// Mutable version of [`Self::at()`].
+    pub fn at_mut(&mut self, index: IndexT) -> Result<&mut DataT, IndexError> {
+        match self.vec.get_mut(index.cast_to()) {
+            Some(Slot::Alive(data)) => Ok(data),
+            Some(Slot::Dead { .. }) => Err(IndexError::Dead),
+            None => Err(IndexError::OutOfBounds),
+        }
+    }
+
+    /// Same as [`Self::get()`], but takes the raw position of the slot instead of `IndexT`.
+    pub fn get_by_usize(&self, index: usize) -> Option<&DataT> {
+        self.vec.get(index).and_then(|slot| match slot {
+            Slot::Alive(data) => Some(data),
+            Slot::Dead { .. } => None,
+        })
+    }
+
+    /// Same as [`Self::get_mut()`], but takes the raw position of the slot instead of `IndexT`.
+    pub fn get_mut_by_usize(&mut self, index: usize) -> Option<&mut DataT> {
+        self.vec.get_mut(index).and_then(|slot| match slot {
+            Slot::Alive(data) => Some(data),
+            Slot::Dead { .. } => None,
+        })
+    }
+
+    /**
+    The content of every slot in positional order, `None` for the dead ones, without allocating.
+    Unlike [`Self::iter()`], the n-th element always corresponds to the id `n`, so it lines up with slice-based code.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abc".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(1);
+
+    assert!(tec.iter_all().eq([Some(&'a'), None, Some(&'c')]));
+    assert_eq!(tec.option_at(1), None);
+    assert_eq!(tec.option_at(2), Some(&'c'));
+    ```
+    */
+    pub fn iter_all(&self) -> impl DoubleEndedIterator<Item = Option<&DataT>> + ExactSizeIterator {
+        self.vec.iter().map(|slot| match slot {
+            Slot::Alive(data) => Some(data),
+            Slot::Dead { .. } => None,
+        })
+    }
+
+    /// The element of [`Self::iter_all()`] at position `index`, i.e. the same as [`Self::get_by_usize()`].
+    pub fn option_at(&self, index: usize) -> Option<&DataT> {
+        self.get_by_usize(index)
+    }
+
+    /**
+    Get mutable references to several items at once, in the same order as `ids`.
+    Returns `None` if any of the ids is dead or appears more than once. Only the requested slots are visited.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, i32> = Default::default();
+    let a = tec.alloc(1);
+    let b = tec.alloc(2);
+
+    let [x, y]: [&mut i32; 2] = tec.get_disjoint_mut_vec(&[b, a]).unwrap().try_into().unwrap();
+    std::mem::swap(x, y);
+    assert_eq!(tec[a], 2);
+
+    assert!(tec.get_disjoint_mut_vec(&[a, a]).is_none());
+    ```
+    */
+    pub fn get_disjoint_mut_vec(&mut self, ids: &[IndexT]) -> Option<Vec<&mut DataT>> {
+        // (position in the storage, position in the result), visited in storage order
+        let mut wanted: Vec<(usize, usize)> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.cast_to(), i))
+            .collect();
+        wanted.sort_unstable();
+        if wanted.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return None;
+        }
+
+        let mut result: Vec<Option<&mut DataT>> = ids.iter().map(|_| None).collect();
+        let mut rest = self.vec.as_mut_slice();
+        let mut offset = 0;
+        for (position, i) in wanted {
+            let (slot, tail) = mem::take(&mut rest)
+                .get_mut(position - offset..)?
+                .split_first_mut()?;
+            match slot {
+                Slot::Alive(data) => result[i] = Some(data),
+                Slot::Dead { .. } => return None,
+            }
+            rest = tail;
+            offset = position + 1;
+        }
+
+        result.into_iter().collect()
+    }
+
+    /// Iterate the living items in ascending order of their ids, see [`Self::iter_with_id()`].
+    pub fn iter(&self) -> impl Iterator<Item = &DataT> + DoubleEndedIterator + ExactSizeIterator {
+        let iter = self.iter_positions().map(|(_, _, data)| data);
+        ExactLen::new(iter, self.count)
+    }
+
+    /**
+    Iterate the living items along with their ids. The ids are guaranteed to come in strictly ascending order,
+    regardless of the dead slots in between or the order the items were allocated in,
+    so two `Tec`s can be merge-joined on their ids. See [`Self::iter_with_id_rev()`] for the descending order.
+    */
+    pub fn iter_with_id(
+        &self,
+    ) -> impl Iterator<Item = (IndexT, &DataT)> + DoubleEndedIterator + ExactSizeIterator {
+        let iter = self.iter_positions().map(|(_, id, data)| (id, data));
+        ExactLen::new(iter, self.count)
+    }
+
+    /**
+    Same as [`Self::iter_with_id()`], but the ids are guaranteed to come in strictly descending order.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcd".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(2);
+
+    assert!(tec.iter_with_id_rev().eq([(3, &'d'), (1, &'b'), (0, &'a')]));
+    ```
+    */
+    pub fn iter_with_id_rev(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (IndexT, &DataT)> + ExactSizeIterator {
+        self.iter_with_id().rev()
+    }
+
+    /**
+    Same as [`Self::iter_with_id()`], but starts at `start`, i.e. only yields the items whose ids are at least `start`.
+    Nothing is yielded if `start` is past the end of the storage. This allows resuming an iteration from a saved id,
+    e.g. to spread the work over several frames.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcde".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(3);
+
+    assert!(tec.iter_from(2).eq([(2, &'c'), (4, &'e')]));
+    assert_eq!(tec.iter_from(5).count(), 0);
+    ```
+    */
+    pub fn iter_from(&self, start: IndexT) -> impl DoubleEndedIterator<Item = (IndexT, &DataT)> {
+        let start = start.cast_to().min(self.vec.len());
+
+        #[cfg(not(feature = "fast-iter"))]
+        let iter =
+            self.vec[start..]
+                .iter()
+                .enumerate()
+                .filter_map(move |(offset, slot)| match slot {
+                    Slot::Alive(data) => Some((IndexT::cast_from(start + offset), data)),
+                    Slot::Dead { .. } => None,
+                });
+
+        #[cfg(feature = "fast-iter")]
+        let iter = self
+            .live
+            .positions_in(start..self.vec.len())
+            .map(|position| match &self.vec[position] {
+                Slot::Alive(data) => (IndexT::cast_from(position), data),
+                Slot::Dead { .. } => unreachable!("dead slot marked as alive"),
+            });
+
+        iter
+    }
+
+    /**
+    Count the living items that satisfy `f`, i.e. a shorthand for `iter().filter(..).count()`.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, i32> = Default::default();
+    [-1, 2, 3, -4].into_iter().for_each(|hp| {
+        tec.alloc(hp);
+    });
+    assert_eq!(tec.count_by(|hp| *hp > 0), 2);
+    ```
+    */
+    pub fn count_by<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&DataT) -> bool,
+    {
+        self.iter().filter(|data| f(data)).count()
+    }
+
+    /**
+    Collect clones of the living items along with their ids into a [`HashSet`], e.g. to compare the content of two
+    collections regardless of the order, see [`Self::entries_ref_set()`] for the borrowing version.
+
+    ```
+    use std::collections::HashSet;
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    tec.alloc('a');
+    tec.alloc('b');
+    tec.remove(0);
+
+    assert_eq!(tec.entries_set(), HashSet::from([(1, 'b')]));
+    ```
+    */
+    pub fn entries_set(&self) -> HashSet<(IndexT, DataT)>
+    where
+        IndexT: Hash,
+        DataT: Clone + Hash + Eq,
+    {
+        self.iter_with_id()
+            .map(|(id, data)| (id, data.clone()))
+            .collect()
+    }
+
+    /// Same as [`Self::entries_set()`], but collects references to the items.
+    pub fn entries_ref_set(&self) -> HashSet<(IndexT, &DataT)>
+    where
+        IndexT: Hash,
+        DataT: Hash + Eq,
+    {
+        self.iter_with_id().collect()
+    }
+
+    /**
+    Creates a view that only sees the living items satisfying `pred`, e.g. to hand "the visible entities" to some
+    rendering code. The predicate is evaluated lazily on every access.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, i32> = Default::default();
+    let negative = tec.alloc(-1);
+    let positive = tec.alloc(1);
+
+    let view = tec.view(|data| *data > 0);
+    assert_eq!(view.get(negative), None);
+    assert_eq!(view.get(positive), Some(&1));
+    assert!(view.iter_with_id().eq([(positive, &1)]));
+    ```
+    */
+    pub fn view<P>(&self, pred: P) -> TecView<'_, IndexT, DataT, P>
+    where
+        P: Fn(&DataT) -> bool,
+    {
+        TecView { tec: self, pred }
+    }
+
+    /**
+    Split the storage into two views that can be mutated independently, e.g. from two threads, similar to
+    [`slice::split_at_mut()`]. The first view covers the ids in `0..mid`, and the second one covers the rest.
+    Panic if `mid` is greater than [`Self::capacity()`].
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, u32> = (0..10).collect();
+    let (mut low, mut high) = tec.split_at_mut(5);
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| low.iter_mut().for_each(|data| *data += 100));
+        scope.spawn(|| high.iter_mut().for_each(|data| *data += 200));
+    });
+    assert_eq!(tec[4], 104);
+    assert_eq!(tec[5], 205);
+    ```
+    */
+    pub fn split_at_mut(
+        &mut self,
+        mid: IndexT,
+    ) -> (TecMutView<'_, IndexT, DataT>, TecMutView<'_, IndexT, DataT>) {
+        let mid = mid.cast_to();
+        assert!(mid <= self.capacity(), "splitting beyond the capacity");
+
+        let (low, high) = self.vec.split_at_mut(mid);
+        (
+            TecMutView::new(
+                low,
+                0,
+                #[cfg(feature = "fast-iter")]
+                &self.live,
+            ),
+            TecMutView::new(
+                high,
+                mid,
+                #[cfg(feature = "fast-iter")]
+                &self.live,
+            ),
+        )
+    }
+
+    /// Similar to [`Self::iter_with_id()`], but also yields the raw position of each item in the underlying storage,
+    /// which is handy for indexing parallel `Vec`s without casting back and forth.
+    pub fn iter_positions(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (usize, IndexT, &DataT)> + ExactSizeIterator {
+        #[cfg(not(feature = "fast-iter"))]
+        let iter = self
+            .vec
+            .iter()
+            .enumerate()
+            .filter_map(|(position, data)| match data {
+                Slot::Alive(data) => Some((position, IndexT::cast_from(position), data)),
+                Slot::Dead { .. } => None,
+            });
+
+        // jump from one living slot to the next, rather than visiting the dead ones
+        #[cfg(feature = "fast-iter")]
+        let iter = self
+            .live
+            .positions()
+            .map(|position| match &self.vec[position] {
+                Slot::Alive(data) => (position, IndexT::cast_from(position), data),
+                Slot::Dead { .. } => unreachable!("dead slot marked as alive"),
+            });
+
+        ExactLen::new(iter, self.count)
+    }
+
+    /**
+    Group the living items into batches of `size`, in ascending order of their ids. The last batch may be shorter.
+    Each batch is collected into a `Vec` of references, since the items are interleaved with dead slots in general.
+    The items themselves are never copied, and for a compact `Tec` (see [`Self::is_compact()`]) each batch covers
+    a contiguous run of slots.
+
+    Panic if `size` is 0.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcde".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(1);
+
+    let batches: Vec<_> = tec.chunks(2).collect();
+    assert_eq!(batches, [vec![(0, &'a'), (2, &'c')], vec![(3, &'d'), (4, &'e')]]);
+    ```
+    */
+    pub fn chunks(&self, size: usize) -> impl Iterator<Item = Vec<(IndexT, &DataT)>> {
+        assert!(size != 0, "chunk size must be non-zero");
+
+        let mut entries = self.iter_with_id();
+        std::iter::from_fn(move || {
+            let chunk: Vec<_> = entries.by_ref().take(size).collect();
+            (!chunk.is_empty()).then_some(chunk)
+        })
+    }
+
+    /// The living item with the smallest id.
+    pub fn first(&self) -> Option<(IndexT, &DataT)> {
+        self.iter_with_id().next()
+    }
+
+    /// The living item with the largest id.
+    pub fn last(&self) -> Option<(IndexT, &DataT)> {
+        self.iter_with_id().next_back()
+    }
+
+    /// Mutable version of [`Self::first()`].
+    pub fn first_mut(&mut self) -> Option<(IndexT, &mut DataT)> {
+        self.iter_mut_with_id().next()
+    }
+
+    /// Mutable version of [`Self::last()`].
+    pub fn last_mut(&mut self) -> Option<(IndexT, &mut DataT)> {
+        self.iter_mut_with_id().next_back()
+    }
+
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut DataT> + DoubleEndedIterator + ExactSizeIterator {
+        self.iter_mut_with_id().map(|(_, data)| data)
+    }
+
+    pub fn iter_mut_with_id(
+        &mut self,
+    ) -> impl Iterator<Item = (IndexT, &mut DataT)> + DoubleEndedIterator + ExactSizeIterator {
+        #[cfg(not(feature = "fast-iter"))]
+        let iter = self
+            .vec
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(id, data)| match data {
+                Slot::Alive(data) => Some((CastUsize::cast_from(id), data)),
+                Slot::Dead { .. } => None,
+            });
+
+        #[cfg(feature = "fast-iter")]
+        let iter = live_bits::LiveSlots::new(self.vec.iter_mut(), self.live.positions(), 0).map(
+            |(position, slot)| match slot {
+                Slot::Alive(data) => (CastUsize::cast_from(position), data),
+                Slot::Dead { .. } => unreachable!("dead slot marked as alive"),
+            },
+        );
+
+        ExactLen::new(iter, self.count)
+    }
+
+    pub fn into_iter_with_id(
+        self,
+    ) -> impl Iterator<Item = (IndexT, DataT)> + DoubleEndedIterator + ExactSizeIterator {
+        #[cfg(not(feature = "fast-iter"))]
+        let iter = self
+            .vec
+            .into_iter()
+            .enumerate()
+            .filter_map(|(id, data)| match data {
+                Slot::Alive(data) => Some((CastUsize::cast_from(id), data)),
+                Slot::Dead { .. } => None,
+            });
+
+        #[cfg(feature = "fast-iter")]
+        let iter = live_bits::LiveSlots::new(self.vec.into_iter(), self.live.into_positions(), 0)
+            .map(|(position, slot)| match slot {
+                Slot::Alive(data) => (CastUsize::cast_from(position), data),
+                Slot::Dead { .. } => unreachable!("dead slot marked as alive"),
+            });
+
+        ExactLen::new(iter, self.count)
+    }
+
+    /**
+    Returns the ids of all living items in ascending order. The result is cached until the next time a slot is allocated
+    or removed (including [`Self::coalesce()`] and [`Self::clear()`]), so repeated calls in between are O(1)
+    instead of scanning all slots like [`Self::iter_with_id()`] does, which pays off when most slots are dead.
+    */
+    pub fn live_ids_cached(&self) -> &[IndexT] {
+        self.live_ids
+            .get_or_init(|| self.iter_with_id().map(|(id, _)| id).collect())
+    }
+
+    /**
+    Returns the ids of all living items, ordered by applying `f` on their data. The items themselves are not moved,
+    so all ids remain valid. The sort is stable, so items that compare equal are kept in ascending order of their ids.
+    */
+    pub fn sorted_ids_by<F>(&self, mut f: F) -> Vec<IndexT>
+    where
+        F: FnMut(&DataT, &DataT) -> Ordering,
+    {
+        let mut entries: Vec<_> = self.iter_with_id().collect();
+        entries.sort_by(|(_, a), (_, b)| f(a, b));
+        entries.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// The amount of occupied space in the underlying `vec`.
+    /// Note:
+    /// ```compile_fail
+    /// self.len() <= self.capacity() == self.vec.len() <= self.vec.capacity()
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// The number of dead slots, i.e. `self.capacity() - self.len()`.
+    /// These are the tombstones that [`Self::alloc()`] reuses and [`Self::coalesce()`] gets rid of.
+    pub fn dead_count(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    /**
+    The number of slots that can still be appended before running out of ids, i.e. the maximum number of slots
+    that `IndexT` can address minus [`Self::capacity()`]. Together with [`Self::dead_count()`], this tells how many
+    more items can be allocated, so that a bulk fill can be checked up front.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    let a = tec.alloc('a');
+    tec.alloc('b');
+    tec.remove(a);
+
+    let max_slots = if cfg!(feature = "full-range") { 256 } else { 255 };
+    assert_eq!(tec.remaining_capacity(), max_slots - 2);
+    ```
+    */
+    pub fn remaining_capacity(&self) -> usize {
+        free_link::max_slots::<IndexT>() - self.capacity()
+    }
+
+    /// Fail fast before a bulk allocation of at least `additional` items that's bound to run out of ids.
+    fn assert_room_for(&self, additional: usize) {
+        assert!(
+            additional <= self.dead_count() + self.remaining_capacity(),
+            "cannot allocate {} more items: {} can only address {} slots, and {} of them are taken",
+            additional,
+            std::any::type_name::<IndexT>(),
+            free_link::max_slots::<IndexT>(),
+            self.len(),
+        );
+    }
+
+    /// Whether there are no dead slots, i.e. the ids of the living items are exactly `0..self.len()`.
+    /// This always holds after [`Self::coalesce()`].
+    pub fn is_compact(&self) -> bool {
+        self.dead_count() == 0
+    }
+
+    /// Panic if there are dead slots, see [`Self::is_compact()`].
+    pub fn assert_compact(&self) {
+        assert!(
+            self.is_compact(),
+            "Tec is not compact: {} dead slot(s) out of {}",
+            self.dead_count(),
+            self.capacity()
+        );
+    }
+
+    /// The number of slots the underlying `vec` can hold without reallocating, i.e. [`Vec::capacity()`].
+    /// Not to be confused with [`Self::capacity()`], which counts both living and dead slots.
+    /// ```compile_fail
+    /// self.len() + self.dead_count() == self.capacity() <= self.allocated_capacity()
+    /// ```
+    pub fn allocated_capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Reserves space for at least `additional` more slots, see [`Vec::reserve()`].
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional);
+    }
+
+    /// Reserves space for exactly `additional` more slots, see [`Vec::reserve_exact()`].
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.vec.reserve_exact(additional);
+    }
+
+    /// Shrinks the underlying `vec` as much as possible, see [`Vec::shrink_to_fit()`].
+    /// Dead slots are kept, so call [`Self::coalesce()`] beforehand to get rid of them.
+    pub fn shrink_to_fit(&mut self) {
+        self.vec.shrink_to_fit();
+    }
+
+    /// Shrinks the underlying `vec` while keeping room for at least `min_capacity` slots, see [`Vec::shrink_to()`].
+    /// Only [`Self::allocated_capacity()`] is affected; [`Self::capacity()`] stays the same since no slot is removed.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.vec.shrink_to(min_capacity);
+    }
+
+    /// The number of bytes allocated by the underlying `vec`, i.e. [`Self::allocated_capacity()`] times the size of a slot.
+    /// This doesn't include heap memory owned by the items themselves.
+    pub fn capacity_bytes(&self) -> usize {
+        self.allocated_capacity() * mem::size_of::<Slot<DataT, IndexT>>()
+    }
+
+    fn get_free_list(&self) -> Vec<IndexT> {
+        let capacity = self.capacity();
+        let len = self.len();
+        assert!(capacity >= len);
+
+        let mut acc = Vec::with_capacity(capacity - len);
+        acc.extend(self.free_list());
+        acc
+    }
+
+    /**
+    The maximal runs of dead slots, by their positions in ascending order, e.g. to visualize the fragmentation.
+    Unlike [`Self::free_list()`], this doesn't depend on the order the slots were freed.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcdef".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    [4, 1, 2].into_iter().for_each(|id| {
+        tec.remove(id);
+    });
+
+    assert_eq!(tec.dead_ranges().collect::<Vec<_>>(), [1..3, 4..5]);
+    ```
+    */
+    pub fn dead_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let mut slots = self.vec.iter().enumerate().peekable();
+
+        std::iter::from_fn(move || {
+            let (start, _) = slots.find(|(_, slot)| matches!(slot, Slot::Dead { .. }))?;
+
+            let mut end = start + 1;
+            while slots
+                .next_if(|(_, slot)| matches!(slot, Slot::Dead { .. }))
+                .is_some()
+            {
+                end += 1;
+            }
+
+            Some(start..end)
+        })
+    }
+
+    /**
+    Lazily walk the free list, i.e. the ids of the dead slots in the order [`Self::alloc()`] would reuse them,
+    which is generally not the order they are laid out in memory.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcd".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(0);
+    tec.remove(2);
+
+    assert_eq!(tec.free_list().collect::<Vec<_>>(), [2, 0]);
+    ```
+    */
+    pub fn free_list(&self) -> impl Iterator<Item = IndexT> + '_ {
+        let mut cur = self.next_free;
+
+        std::iter::from_fn(move || {
+            let cur_index = free_link::target(cur)?;
+
+            match &self.vec[cur_index.cast_to()] {
+                Slot::Dead { next_free } => cur = *next_free,
+                Slot::Alive(_) => unreachable!("found a living slot in free list"),
+            }
+
+            Some(cur_index)
+        })
+    }
+
+    /**
+    Coalescing using the typical typical 2 direction trick, and then return the number of items being removed.
+    - FORWARD: we need to backfill dead slots in increasing order, using a binary heap
+    - another cursor traverse from the back of the memory block to scan for living slots and do the swap
+
+    However, if you can bound the number of dead slots to k=log(n), then you can bound this to O(log n). Analysis:
+    - forward cusor: it uses a binary heap to iterate, which takes `k log k` = `log(n) * log(log(n))` comparisons, which is O(log(n)), calculation thanks to symbolic calculator.
+    - backward cursor: either it gets k living members, or it has loop through at most k dead members to get the k living memebers, so O(k) = O(log(n))
+    */
+    fn heap_based_coalesce<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        let mut free_heap = {
+            let free_list: Vec<_> = self.get_free_list().into_iter().map(Reverse).collect();
+
+            BinaryHeap::from(free_list)
+        };
+        let removed_len = free_heap.len();
+
+        let mut backward_cursor = self.capacity() - 1;
+        'main_loop: while let Some(Reverse(forward_cursor)) = free_heap.pop() {
+            // find a living slot from the back
+            let mut living_target = loop {
+                let swap_target = &mut self.vec[backward_cursor];
+
+                let forward_cursor_usize = forward_cursor.cast_to();
+                if forward_cursor_usize >= backward_cursor {
+                    break 'main_loop;
+                }
+
+                if matches!(swap_target, Slot::Alive(_)) {
+                    // Let's swap the target out of the vec and replace with garbage data.
+                    // Later self.remove_trailing_dead_slots() will drop them.
+                    let mut dummy = Slot::Dead {
+                        next_free: free_link::end(),
+                    };
+                    mem::swap(swap_target, &mut dummy);
+                    break dummy;
+                }
+
+                backward_cursor -= 1;
+
+                // note: we have at least 1 living slot otherwise the code would short circuit in the base case
+                debug_assert!(backward_cursor != 0);
+            };
+
+            let dead_target = &mut self.vec[forward_cursor.cast_to()];
+            debug_assert!(matches!(dead_target, Slot::Dead { .. }));
+
+            // i.e. doing a remove and swap
+            mem::swap(&mut living_target, dead_target);
+            f(IndexT::cast_from(backward_cursor), forward_cursor);
+        }
+
+        removed_len
+    }
+
+    /**
+    Coalesce the data by removing the dead slots. Takes a function `f(old_id, new_id)`
+    that allows you to deal with changes made by the process, i.e. say in your game model,
+    you have an entity which occupied `old_id`, you would need to change all references
+    to use the `new_id`.
+    This is intended to be used before saving a game.
+
+    The relocations are deterministic, so they can be relied upon, e.g. to migrate save files:
+    the dead slots are filled in increasing order of their ids, each by the living item with the highest id,
+    and `f` is called in that same order.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcdef".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(2);
+    tec.remove(0);
+
+    let mut moves = Vec::new();
+    tec.coalesce(|old_id, new_id| moves.push((old_id, new_id)));
+    assert_eq!(moves, [(5, 0), (4, 2)]);
+    assert!(tec.iter().eq(&['f', 'b', 'e', 'd']));
+    ```
+
+    Note: this algorithm is O(n lg n) due to the use of binary heap.
+    */
+    pub fn coalesce<F>(&mut self, f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        self.coalesce_stats(f);
+    }
+
+    /**
+    Same as [`Self::coalesce()`], followed by [`Self::shrink_to_fit()`] to release the memory that is no longer needed,
+    e.g. after loading a save file, when the transient high-water allocation is gone for good.
+    Prefer [`Self::coalesce()`] if the collection will grow back, since the memory has to be allocated again.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcdef".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(1);
+    tec.remove(2);
+
+    tec.coalesce_and_shrink(|_, _| {});
+    assert_eq!(tec.capacity(), 4);
+    assert!(tec.allocated_capacity() >= 4);
+    ```
+    */
+    pub fn coalesce_and_shrink<F>(&mut self, f: F)
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        self.coalesce(f);
+        self.shrink_to_fit();
+    }
+
+    /**
+    Same as [`Self::coalesce()`], but also tells you how much got compacted, e.g. to tune how often to coalesce.
+
+    ```
+    use stable_id::{CoalesceStats, Tec};
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcde".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(0);
+    tec.remove(2);
+
+    let stats = tec.coalesce_stats(|_, _| {});
+    assert_eq!(
+        stats,
+        CoalesceStats {
+            moved: 2,
+            reclaimed: 2,
+            before_capacity: 5,
+            after_capacity: 3,
+        }
+    );
+    ```
+    */
+    pub fn coalesce_stats<F>(&mut self, mut f: F) -> CoalesceStats
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        self.assert_unsealed();
+
+        let capacity = self.capacity();
+        let mut stats = CoalesceStats {
+            moved: 0,
+            reclaimed: 0,
+            before_capacity: capacity,
+            after_capacity: capacity,
+        };
+
+        let has_dead_slots = free_link::target(self.next_free)
+            .is_some_and(|next_free| next_free.cast_to() < capacity);
+        if !has_dead_slots {
+            return stats;
+        } else {
+            // this implies there is at least 1 living item
+            debug_assert!(!self.is_empty());
+        }
+
+        let mut observer = self.observer.take();
+        let removed_len = self.heap_based_coalesce(|old_id, new_id| {
+            f(old_id, new_id);
+            stats.moved += 1;
+
+            if let Some(observer) = &mut observer {
+                call_observer(
+                    observer,
+                    TecEvent::Relocated {
+                        from: old_id,
+                        to: new_id,
+                    },
+                );
+            }
+        });
+        self.observer = observer;
+
+        self.truncate_relocated(removed_len);
+
+        stats.reclaimed = removed_len;
+        stats.after_capacity = self.capacity();
+        stats
+    }
+
+    /// Drop the last `removed_len` slots, which are either dead or have had their items relocated to the front.
+    fn truncate_relocated(&mut self, removed_len: usize) {
+        let capacity = self.capacity();
+
+        // the items at the back were either dead or relocated
+        #[cfg(feature = "debug-tracking")]
+        (capacity - removed_len..capacity).for_each(|position| self.retire(position));
+
+        // pop out all trailing dead slots
+        self.vec.truncate(capacity - removed_len);
+        #[cfg(feature = "fast-iter")]
+        {
+            self.live = live_bits::LiveBits::compact(self.vec.len());
+        }
+        self.invalidate_live_ids();
+
+        // edge-case: at this point the memory is compact, so we're pointing the free-list to the sentinel value
+        self.set_sentinal();
+
+        debug_assert_eq!(self.len(), self.capacity());
+    }
+
+    /**
+    Same as [`Self::coalesce()`], but the items are relocated by several threads, to shorten the stall of compacting
+    a large collection, e.g. at save time. The relocations are exactly those of [`Self::coalesce()`], but `f` is called
+    from the worker threads in no particular order, hence the `Fn + Sync` bound. The observer, if any, is notified
+    afterward from the calling thread.
+
+    Falls back to [`Self::coalesce()`] when there are too few items to relocate for the threads to pay off,
+    or when there's a single core. Only available with the `parallel` feature.
+
+    ```
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use stable_id::Tec;
+
+    let mut tec: Tec<u32, u64> = Tec::populate(0, 100);
+    (0..100).step_by(2).for_each(|i| {
+        tec.remove(i);
+    });
+
+    let moved = AtomicUsize::new(0);
+    tec.par_coalesce(|_, _| {
+        moved.fetch_add(1, Ordering::Relaxed);
+    });
+    assert_eq!(moved.into_inner(), 25);
+    assert_eq!(tec.capacity(), 50);
+    ```
+    */
+    #[cfg(feature = "parallel")]
+    pub fn par_coalesce<F>(&mut self, f: F)
+    where
+        F: Fn(IndexT, IndexT) + Sync,
+        IndexT: Send,
+        DataT: Send,
+    {
+        const MIN_MOVES_PER_THREAD: usize = 1 << 12;
+
+        self.assert_unsealed();
+
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        self.coalesce_in_threads(threads, MIN_MOVES_PER_THREAD, f);
+    }
+
+    /**
+    The slots left of the final length are the only ones that can be filled, and the ones on the right are the only ones
+    that need to be emptied. Pairing the holes in increasing order with the movers in decreasing order, like
+    [`Self::heap_based_coalesce()`] does, means that a contiguous batch of pairs covers a contiguous range of slots
+    on each side, so every thread gets its own disjoint pair of subslices.
+    */
+    #[cfg(feature = "parallel")]
+    fn coalesce_in_threads<F>(&mut self, max_threads: usize, min_moves_per_thread: usize, f: F)
+    where
+        F: Fn(IndexT, IndexT) + Sync,
+        IndexT: Send,
+        DataT: Send,
+    {
+        // the dead slots bound the number of moves, so a single thread can be ruled out before walking the free list
+        let threads_for = |moves: usize| max_threads.min(moves / min_moves_per_thread.max(1));
+        if threads_for(self.dead_count()) <= 1 {
+            self.coalesce(f);
+            return;
+        }
+
+        let len = self.len();
+        let mut holes: Vec<usize> = self
+            .free_list()
+            .map(CastUsize::cast_to)
+            .filter(|&hole| hole < len)
+            .collect();
+
+        let threads = threads_for(holes.len());
+        if threads <= 1 {
+            self.coalesce(f);
+            return;
+        }
+
+        holes.sort_unstable();
+        let movers: Vec<usize> = self.vec[len..]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, slot)| matches!(slot, Slot::Alive(_)))
+            .map(|(offset, _)| len + offset)
+            .collect();
+        debug_assert_eq!(holes.len(), movers.len());
+
+        let batch_size = holes.len().div_ceil(threads);
+        let (mut head, mut tail) = self.vec.split_at_mut(len);
+        let mut head_start = 0;
+
+        thread::scope(|scope| {
+            let f = &f;
+            for (holes, movers) in holes.chunks(batch_size).zip(movers.chunks(batch_size)) {
+                // the batch owns the slots from head_start up to the last hole on the left,
+                // and from the last mover up to what the previous batch has left on the right
+                let head_end = holes[holes.len() - 1] + 1;
+                let (batch_head, rest) = mem::take(&mut head).split_at_mut(head_end - head_start);
+                head = rest;
+
+                let tail_start = movers[movers.len() - 1];
+                let (rest, batch_tail) = mem::take(&mut tail).split_at_mut(tail_start - len);
+                tail = rest;
+
+                let offset = head_start;
+                scope.spawn(move || {
+                    holes.iter().zip(movers).for_each(|(&hole, &mover)| {
+                        mem::swap(
+                            &mut batch_head[hole - offset],
+                            &mut batch_tail[mover - tail_start],
+                        );
+                        f(IndexT::cast_from(mover), IndexT::cast_from(hole));
+                    });
+                });
+
+                head_start = head_end;
+            }
+        });
+
+        holes.iter().zip(&movers).for_each(|(&hole, &mover)| {
+            self.notify(TecEvent::Relocated {
+                from: IndexT::cast_from(mover),
+                to: IndexT::cast_from(hole),
+            })
+        });
+
+        self.truncate_relocated(self.capacity() - len);
+    }
+
+    /**
+    Incremental version of [`Self::coalesce()`] that relocates at most `max_moves` items, so that the work can be spread
+    over multiple calls, e.g. across frames. Each call picks up where the previous one left off, and the items end up
+    in the same places as [`Self::coalesce()`] would put them. Returns whether there are no dead slots left.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcdef".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+    tec.remove(0);
+    tec.remove(1);
+
+    let mut moves = Vec::new();
+    assert!(!tec.coalesce_budgeted(1, |old_id, new_id| moves.push((old_id, new_id))));
+    assert!(tec.coalesce_budgeted(1, |old_id, new_id| moves.push((old_id, new_id))));
+    assert_eq!(moves, [(5, 0), (4, 1)]);
+    assert_eq!(tec.iter().collect::<String>(), "fecd");
+    ```
+    */
+    pub fn coalesce_budgeted<F>(&mut self, max_moves: usize, mut f: F) -> bool
+    where
+        F: FnMut(IndexT, IndexT),
+    {
+        self.assert_unsealed();
+
+        let mut holes: BinaryHeap<_> = self.free_list().map(Reverse).collect();
+        if holes.is_empty() {
+            return true;
+        }
+
+        let mut observer = self.observer.take();
+        let mut end = self.capacity(); // every slot from `end` onward is dead
+        let mut moves = 0;
+
+        loop {
+            while end > 0 && matches!(self.vec[end - 1], Slot::Dead { .. }) {
+                end -= 1;
+            }
+
+            let hole = match holes.peek() {
+                Some(Reverse(hole)) if hole.cast_to() < end && moves < max_moves => *hole,
+                _ => break,
+            };
+            holes.pop();
+
+            // the hole is dead, so swapping turns the living slot at the back into garbage, which gets truncated
+            let old_position = end - 1;
+            self.vec.swap(hole.cast_to(), old_position);
+            #[cfg(feature = "fast-iter")]
+            {
+                self.live.insert(hole.cast_to());
+                self.live.remove(old_position);
+            }
+            end = old_position;
+            moves += 1;
+
+            let old_id = IndexT::cast_from(old_position);
+            f(old_id, hole);
+            if let Some(observer) = &mut observer {
+                call_observer(
+                    observer,
+                    TecEvent::Relocated {
+                        from: old_id,
+                        to: hole,
+                    },
+                );
+            }
+        }
+        self.observer = observer;
+
+        #[cfg(feature = "debug-tracking")]
+        (end..self.capacity()).for_each(|position| self.retire(position));
+
+        self.vec.truncate(end);
+        self.invalidate_live_ids();
+
+        // relink the holes that are left, so that the smallest one is reused first
+        self.set_sentinal();
+        for Reverse(hole) in holes.into_sorted_vec() {
+            if hole.cast_to() < end {
+                self.vec[hole.cast_to()] = Slot::Dead {
+                    next_free: self.next_free,
+                };
+                self.next_free = free_link::to(hole);
             }
-            Slot::Dead { .. } => panic!("removing a dead item"),
-        };
+        }
 
-        data
-    }
+        debug_assert!(self.check_consistency());
 
-    pub fn get(&self, index: IndexT) -> Option<&DataT> {
-        self.vec.get(index.cast_to()).and_then(|slot| match slot {
-            Slot::Alive(data) => Some(data),
-            Slot::Dead { .. } => None,
-        })
+        self.is_compact()
     }
 
-    pub fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
-        self.vec
-            .get_mut(index.cast_to())
-            .and_then(|slot| match slot {
-                Slot::Alive(data) => Some(data),
-                Slot::Dead { .. } => None,
-            })
-    }
+    /**
+    Keep only the items for which `predicate(id, data)` returns `true`. The others are removed as if by
+    [`Self::remove()`], so the ids of the survivors stay the same. See [`Self::retain_compacting()`] to compact as well.
+    */
+    pub fn retain<P>(&mut self, mut predicate: P)
+    where
+        P: FnMut(IndexT, &DataT) -> bool,
+    {
+        for position in 0..self.capacity() {
+            let id = IndexT::cast_from(position);
 
-    pub fn iter(&self) -> impl Iterator<Item = &DataT> + DoubleEndedIterator {
-        self.vec.iter().filter_map(|data| match data {
-            Slot::Alive(data) => Some(data),
-            Slot::Dead { .. } => None,
-        })
-    }
+            let keep = match &self.vec[position] {
+                Slot::Alive(data) => predicate(id, data),
+                Slot::Dead { .. } => true,
+            };
 
-    pub fn iter_with_id(&self) -> impl Iterator<Item = (IndexT, &DataT)> + DoubleEndedIterator {
-        self.vec
-            .iter()
-            .enumerate()
-            .filter_map(|(id, data)| match data {
-                Slot::Alive(data) => Some((IndexT::cast_from(id), data)),
-                Slot::Dead { .. } => None,
-            })
+            if !keep {
+                self.remove(id);
+            }
+        }
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut DataT> + DoubleEndedIterator {
-        self.vec.iter_mut().filter_map(|data| match data {
-            Slot::Alive(data) => Some(data),
-            Slot::Dead { .. } => None,
-        })
-    }
+    /**
+    Remove every item whose id is not in `keep`, e.g. to only keep the results of a query.
+    The ids of the survivors stay the same. Ids in `keep` that don't refer to a living item are ignored.
 
-    pub fn iter_mut_with_id(
-        &mut self,
-    ) -> impl Iterator<Item = (IndexT, &mut DataT)> + DoubleEndedIterator {
-        self.vec
-            .iter_mut()
-            .enumerate()
-            .filter_map(|(id, data)| match data {
-                Slot::Alive(data) => Some((CastUsize::cast_from(id), data)),
-                Slot::Dead { .. } => None,
-            })
-    }
+    ```
+    use std::collections::HashSet;
+    use stable_id::Tec;
 
-    pub fn into_iter_with_id(self) -> impl Iterator<Item = (IndexT, DataT)> + DoubleEndedIterator {
-        self.vec
-            .into_iter()
-            .enumerate()
-            .filter_map(|(id, data)| match data {
-                Slot::Alive(data) => Some((CastUsize::cast_from(id), data)),
-                Slot::Dead { .. } => None,
-            })
-    }
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcde".chars().for_each(|c| {
+        tec.alloc(c);
+    });
 
-    /// The amount of occupied space in the underlying `vec`.
-    /// Note:
-    /// ```compile_fail
-    /// self.len() <= self.capacity() == self.vec.len() <= self.vec.capacity()
-    /// ```
-    pub fn capacity(&self) -> usize {
-        self.vec.len()
+    tec.retain_ids(&HashSet::from([1, 3, 100]));
+    assert_eq!(tec.iter_with_id().collect::<Vec<_>>(), [(1, &'b'), (3, &'d')]);
+    ```
+    */
+    pub fn retain_ids<S>(&mut self, keep: &HashSet<IndexT, S>)
+    where
+        IndexT: Hash,
+        S: BuildHasher,
+    {
+        self.retain(|id, _| keep.contains(&id));
     }
 
-    fn get_free_list(&self) -> Vec<IndexT> {
-        let max = Maximum::max_value();
-        let capacity = self.capacity();
-        let len = self.len();
-        assert!(capacity >= len);
+    /**
+    Keep only the items for which `predicate(id, data)` returns `true`, and pack the survivors into ids `0..k`
+    in a single pass, reporting every move through `relocate(old_id, new_id)`. So the result has no dead slots,
+    just like calling [`Self::coalesce()`] after removing the rejected items, but without going through the free list.
 
-        let mut cur = self.next_free;
-        let mut acc = Vec::with_capacity(capacity - len);
+    Unlike [`Self::coalesce()`], survivors keep their relative order, and relocations are reported in ascending order.
 
-        loop {
-            if cur == max {
-                break;
-            }
+    ```
+    use stable_id::Tec;
 
-            if let Slot::Dead { next_free } = &self.vec[cur.cast_to()] {
-                acc.push(cur);
-                cur = *next_free;
-            } else {
-                unreachable!("found a living slot in free list")
-            }
-        }
-        acc
-    }
+    let mut tec: Tec<u8, char> = Default::default();
+    "abcde".chars().for_each(|c| {
+        tec.alloc(c);
+    });
 
-    /**
-    Coalescing using the typical typical 2 direction trick, and then return the number of items being removed.
-    - FORWARD: we need to backfill dead slots in increasing order, using a binary heap
-    - another cursor traverse from the back of the memory block to scan for living slots and do the swap
+    let mut moves = Vec::new();
+    tec.retain_compacting(|_, c| *c != 'b', |old_id, new_id| moves.push((old_id, new_id)));
 
-    However, if you can bound the number of dead slots to k=log(n), then you can bound this to O(log n). Analysis:
-    - forward cusor: it uses a binary heap to iterate, which takes `k log k` = `log(n) * log(log(n))` comparisons, which is O(log(n)), calculation thanks to symbolic calculator.
-    - backward cursor: either it gets k living members, or it has loop through at most k dead members to get the k living memebers, so O(k) = O(log(n))
+    assert_eq!(tec.iter().collect::<String>(), "acde");
+    assert_eq!(moves, [(2, 1), (3, 2), (4, 3)]);
+    ```
     */
-    fn heap_based_coalesce<F>(&mut self, mut f: F) -> usize
+    pub fn retain_compacting<P, R>(&mut self, mut predicate: P, mut relocate: R)
     where
-        F: FnMut(IndexT, IndexT),
+        P: FnMut(IndexT, &DataT) -> bool,
+        R: FnMut(IndexT, IndexT),
     {
-        let mut free_heap = {
-            let free_list: Vec<_> = self.get_free_list().into_iter().map(Reverse).collect();
+        self.assert_unsealed();
 
-            BinaryHeap::from(free_list)
-        };
-        let removed_len = free_heap.len();
+        let mut observer = self.observer.take();
+        let mut position = 0;
+        let mut kept = 0;
+        #[cfg(feature = "debug-tracking")]
+        let mut retired = Vec::new();
 
-        let mut backward_cursor = self.capacity() - 1;
-        let max = Maximum::max_value();
-        'main_loop: while let Some(Reverse(forward_cursor)) = free_heap.pop() {
-            // find a living slot from the back
-            let mut living_target = loop {
-                let swap_target = &mut self.vec[backward_cursor];
+        // Vec::retain() visits the slots exactly once in order, so the survivors end up at 0..kept
+        self.vec.retain(|slot| {
+            let old_id = IndexT::cast_from(position);
+            position += 1;
 
-                let forward_cursor_usize = forward_cursor.cast_to();
-                if forward_cursor_usize >= backward_cursor {
-                    break 'main_loop;
+            let Slot::Alive(data) = slot else {
+                return false;
+            };
+
+            let event = if predicate(old_id, data) {
+                let new_id = IndexT::cast_from(kept);
+                kept += 1;
+
+                if old_id == new_id {
+                    return true;
                 }
 
-                if matches!(swap_target, Slot::Alive(_)) {
-                    // Let's swap the target out of the vec and replace with garbage data.
-                    // Later self.remove_trailing_dead_slots() will drop them.
-                    let mut dummy = Slot::Dead { next_free: max };
-                    mem::swap(swap_target, &mut dummy);
-                    break dummy;
+                relocate(old_id, new_id);
+                TecEvent::Relocated {
+                    from: old_id,
+                    to: new_id,
                 }
+            } else {
+                TecEvent::Removed(old_id)
+            };
 
-                backward_cursor -= 1;
+            if let Some(observer) = &mut observer {
+                call_observer(observer, event);
+            }
+            #[cfg(feature = "debug-tracking")]
+            retired.push(old_id.cast_to());
 
-                // note: we have at least 1 living slot otherwise the code would short circuit in the base case
-                debug_assert!(backward_cursor != 0);
-            };
+            matches!(event, TecEvent::Relocated { .. })
+        });
+        self.observer = observer;
 
-            let dead_target = &mut self.vec[forward_cursor.cast_to()];
-            debug_assert!(matches!(dead_target, Slot::Dead { .. }));
+        #[cfg(feature = "debug-tracking")]
+        retired
+            .into_iter()
+            .for_each(|position| self.retire(position));
 
-            // i.e. doing a remove and swap
-            mem::swap(&mut living_target, dead_target);
-            f(IndexT::cast_from(backward_cursor), forward_cursor);
+        self.count = kept;
+        #[cfg(feature = "fast-iter")]
+        {
+            self.live = live_bits::LiveBits::compact(kept);
         }
+        self.invalidate_live_ids();
+        self.set_sentinal();
 
-        removed_len
+        debug_assert_eq!(self.len(), self.capacity());
+        debug_assert!(self.check_consistency());
     }
 
     /**
-    Coalesce the data by removing the dead slots. Takes a function `f(old_id, new_id)`
-    that allows you to deal with changes made by the process, i.e. say in your game model,
-    you have an entity which occupied `old_id`, you would need to change all references
-    to use the `new_id`.
-    This is intended to be used before saving a game.
+    Reorder the underlying storage so that the items are sorted by `cmp`, which improves locality when the items are
+    visited in that order. Every item that changes its id is reported through `relocate(old_id, new_id)`.
+    Since the moves form a permutation, treat the reported pairs as a mapping from the ids before the sort to the ids
+    after it, rather than applying them one at a time in place. The sort is stable.
 
-    Note: this algorithm is O(n lg n) due to the use of binary heap.
+    Panic if there are dead slots, so call [`Self::coalesce()`] first.
+
+    ```
+    use stable_id::Tec;
+
+    let mut tec: Tec<u8, char> = Default::default();
+    "cab".chars().for_each(|c| {
+        tec.alloc(c);
+    });
+
+    let mut moves = Vec::new();
+    tec.sort_storage_by(|a, b| a.cmp(b), |old_id, new_id| moves.push((old_id, new_id)));
+
+    assert_eq!(tec.iter().collect::<String>(), "abc");
+    assert_eq!(moves, [(1, 0), (2, 1), (0, 2)]);
+    ```
     */
-    pub fn coalesce<F>(&mut self, f: F)
+    pub fn sort_storage_by<F, G>(&mut self, mut cmp: F, mut relocate: G)
     where
-        F: FnMut(IndexT, IndexT),
+        F: FnMut(&DataT, &DataT) -> Ordering,
+        G: FnMut(IndexT, IndexT),
     {
-        let next_usize = self.next_free.cast_to();
-        let capacity = self.capacity();
-        if next_usize >= capacity {
-            return;
-        } else {
-            // this implies there is at least 1 living item
-            debug_assert!(!self.is_empty());
-        }
-
-        let removed_len = self.heap_based_coalesce(f);
+        self.assert_unsealed();
+        assert_eq!(
+            self.len(),
+            self.capacity(),
+            "sorting the storage with dead slots"
+        );
 
-        // pop out all trailing dead slots
-        self.vec.truncate(capacity - removed_len);
+        fn data_at<DataT, IndexT>(slot: &Slot<DataT, IndexT>) -> &DataT {
+            match slot {
+                Slot::Alive(data) => data,
+                Slot::Dead { .. } => unreachable!("found a dead slot in a compact storage"),
+            }
+        }
 
-        // edge-case: at this point the memory is compact, so we're pointing the free-list to the sentinel value
-        self.set_sentinal();
+        // order[new_position] == old_position
+        let mut order: Vec<usize> = (0..self.capacity()).collect();
+        order.sort_by(|&a, &b| cmp(data_at(&self.vec[a]), data_at(&self.vec[b])));
+
+        for (new_position, &old_position) in order.iter().enumerate() {
+            if new_position != old_position {
+                let (old_id, new_id) = (
+                    IndexT::cast_from(old_position),
+                    IndexT::cast_from(new_position),
+                );
+                relocate(old_id, new_id);
+                #[cfg(feature = "debug-tracking")]
+                self.retire(old_position);
+                self.notify(TecEvent::Relocated {
+                    from: old_id,
+                    to: new_id,
+                });
+            }
+        }
 
-        debug_assert_eq!(self.len(), self.capacity());
+        let mut slots: Vec<_> = mem::take(&mut self.vec).into_iter().map(Some).collect();
+        self.vec = order
+            .into_iter()
+            .map(|old_position| slots[old_position].take().expect("moving a slot twice"))
+            .collect();
     }
 
     fn check_consistency(&self) -> bool {
@@ -337,8 +2117,16 @@ where
 
         debug_assert!(self.check_free_link_invariant(self.next_free));
 
+        #[cfg(feature = "fast-iter")]
+        debug_assert!(self.live.positions().eq(self
+            .vec
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| matches!(slot, Slot::Alive(_)))
+            .map(|(i, _)| i)));
+
         if self.is_empty() {
-            debug_assert!(self.next_free == IndexT::max_value());
+            debug_assert!(free_link::target(self.next_free).is_none());
             debug_assert!(self.vec.is_empty());
             return true;
         }
@@ -369,7 +2157,7 @@ where
 
 impl<IndexT, DataT> Tec<IndexT, DataT>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum,
+    IndexT: TecIndex,
     DataT: Clone,
 {
     /**
@@ -381,15 +2169,23 @@ where
 
         Self {
             vec,
-            next_free: Maximum::max_value(),
+            next_free: free_link::end(),
+            total_allocated: count as u64,
             count,
+            observer: None,
+            sealed: false,
+            live_ids: OnceLock::new(),
+            #[cfg(feature = "debug-tracking")]
+            generations: Vec::new(),
+            #[cfg(feature = "fast-iter")]
+            live: live_bits::LiveBits::compact(count),
         }
     }
 }
 
 impl<IndexT, DataT> Tec<IndexT, DataT>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum,
+    IndexT: TecIndex,
     DataT: Clone + Default,
 {
     /**
@@ -402,17 +2198,108 @@ where
 
 impl<IndexT, DataT> Tec<IndexT, DataT>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum,
+    IndexT: TecIndex,
     DataT: Default,
 {
     pub fn alloc_default(&mut self) -> IndexT {
         self.alloc(Default::default())
     }
+
+    /**
+    Make a copy that has the exact same layout (living and dead slots, free list, etc.), but with
+    every living item replaced by the default value. Useful when the data are recomputed from scratch but the ids
+    must match, like double-buffering.
+    */
+    pub fn clone_skeleton(&self) -> Self {
+        let vec = self
+            .vec
+            .iter()
+            .map(|slot| match slot {
+                Slot::Alive(_) => Slot::Alive(Default::default()),
+                Slot::Dead { next_free } => Slot::Dead {
+                    next_free: *next_free,
+                },
+            })
+            .collect();
+
+        Self {
+            vec,
+            next_free: self.next_free,
+            count: self.count,
+            total_allocated: self.total_allocated,
+            observer: None,
+            sealed: self.sealed,
+            live_ids: self.live_ids.clone(),
+            #[cfg(feature = "debug-tracking")]
+            generations: self.generations.clone(),
+            #[cfg(feature = "fast-iter")]
+            live: self.live.clone(),
+        }
+    }
+}
+
+/**
+A `Tec<IndexT, ()>` works as a set of ids: the slots hold no data, so the storage is only as big as the free list links,
+and coalescing still reports every relocation even though there's nothing to move.
+
+```
+use stable_id::Tec;
+
+let mut ids: Tec<u8, ()> = Default::default();
+let a = ids.insert_marker();
+let b = ids.insert_marker();
+ids.remove(a);
+
+assert!(ids.get(b).is_some());
+assert!(ids.get(a).is_none());
+assert_eq!(ids.insert_marker(), a);
+```
+*/
+impl<IndexT> Tec<IndexT, ()>
+where
+    IndexT: TecIndex,
+{
+    /// Issue an id, i.e. `alloc(())`.
+    pub fn insert_marker(&mut self) -> IndexT {
+        self.alloc(())
+    }
+}
+
+impl<IndexT, DataT> StableCollection<IndexT, DataT> for Tec<IndexT, DataT>
+where
+    IndexT: TecIndex,
+{
+    fn alloc(&mut self, data: DataT) -> IndexT {
+        Tec::alloc(self, data)
+    }
+
+    fn get(&self, index: IndexT) -> Option<&DataT> {
+        Tec::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
+        Tec::get_mut(self, index)
+    }
+
+    fn remove(&mut self, index: IndexT) -> Option<DataT> {
+        Tec::take(self, index)
+    }
+
+    fn len(&self) -> usize {
+        Tec::len(self)
+    }
+
+    fn iter_with_id<'a>(&'a self) -> impl Iterator<Item = (IndexT, &'a DataT)>
+    where
+        DataT: 'a,
+    {
+        Tec::iter_with_id(self)
+    }
 }
 
 impl<IndexT, DataT> Index<IndexT> for Tec<IndexT, DataT>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum,
+    IndexT: TecIndex,
 {
     type Output = DataT;
 
@@ -423,13 +2310,136 @@ where
 
 impl<IndexT, DataT> IndexMut<IndexT> for Tec<IndexT, DataT>
 where
-    IndexT: CastUsize + Ord + Copy + Maximum,
+    IndexT: TecIndex,
 {
     fn index_mut(&mut self, index: IndexT) -> &mut Self::Output {
         self.get_mut(index).expect("element not exist")
     }
 }
 
+impl<IndexT, DataT> FromIterator<DataT> for Tec<IndexT, DataT>
+where
+    IndexT: TecIndex,
+{
+    fn from_iter<I: IntoIterator<Item = DataT>>(iter: I) -> Self {
+        let mut result = Self::default();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<IndexT, DataT> Extend<DataT> for Tec<IndexT, DataT>
+where
+    IndexT: TecIndex,
+{
+    /// Allocates every item of `iter`, see [`Tec::alloc()`]. Space is reserved up front based on the lower bound
+    /// of `size_hint()`, minus the dead slots that will be filled first.
+    /// Panics right away if that lower bound already exceeds the ids left, see [`Tec::remaining_capacity()`].
+    fn extend<I: IntoIterator<Item = DataT>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        self.assert_room_for(lower);
+        self.reserve(lower.saturating_sub(self.dead_count()));
+
+        iter.for_each(|data| {
+            self.alloc(data);
+        });
+    }
+}
+
+impl Display for CapacityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "requested capacity {} exceeds the maximum of {} items addressable by the index type",
+            self.requested, self.max
+        )
+    }
+}
+
+impl Error for CapacityError {}
+
+impl Display for ReinsertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReinsertError::Occupied => write!(f, "the slot is occupied"),
+            ReinsertError::OutOfBounds => write!(f, "the id is out of bounds"),
+            ReinsertError::NeverIssued => write!(f, "id has never been issued"),
+        }
+    }
+}
+
+impl Error for ReinsertError {}
+
+impl Display for SealedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tec is sealed")
+    }
+}
+
+impl Error for SealedError {}
+
+impl Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexError::OutOfBounds => write!(f, "the id is out of bounds"),
+            IndexError::Dead => write!(f, "the slot is dead"),
+        }
+    }
+}
+
+impl Error for IndexError {}
+
+impl<IndexT> Display for OverlapError<IndexT>
+where
+    IndexT: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "id {} is taken by both collections", self.id)
+    }
+}
+
+impl<IndexT> Error for OverlapError<IndexT> where IndexT: Debug + Display {}
+
+impl<IndexT, DataT> Clone for Tec<IndexT, DataT>
+where
+    IndexT: Clone,
+    DataT: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            vec: self.vec.clone(),
+            next_free: self.next_free.clone(),
+            count: self.count,
+            total_allocated: self.total_allocated,
+            observer: None,
+            sealed: self.sealed,
+            live_ids: self.live_ids.clone(),
+            #[cfg(feature = "debug-tracking")]
+            generations: self.generations.clone(),
+            #[cfg(feature = "fast-iter")]
+            live: self.live.clone(),
+        }
+    }
+
+    /**
+    Overwrites `self` with a copy of `source`, reusing the existing allocation of the underlying `vec`
+    instead of dropping it and allocating a new one. The observer of `self` is kept as is.
+    */
+    fn clone_from(&mut self, source: &Self) {
+        self.vec.clone_from(&source.vec);
+        self.next_free.clone_from(&source.next_free);
+        self.count = source.count;
+        self.total_allocated = source.total_allocated;
+        self.sealed = source.sealed;
+        self.live_ids.clone_from(&source.live_ids);
+        #[cfg(feature = "debug-tracking")]
+        self.generations.clone_from(&source.generations);
+        #[cfg(feature = "fast-iter")]
+        self.live.clone_from(&source.live);
+    }
+}
+
 impl<IndexT, DataT> Debug for Tec<IndexT, DataT>
 where
     IndexT: Debug,
@@ -443,3 +2453,30 @@ where
             .finish()
     }
 }
+
+/**
+Print the living items as `id: data` lines in ascending order of their ids, leaving out the dead slots.
+Use [`Debug`] instead to inspect the internal layout.
+
+```
+use stable_id::Tec;
+
+let mut tec: Tec<u8, char> = Default::default();
+let a = tec.alloc('a');
+tec.alloc('b');
+tec.alloc('c');
+tec.remove(a);
+
+assert_eq!(tec.to_string(), "1: b\n2: c\n");
+```
+*/
+impl<IndexT, DataT> Display for Tec<IndexT, DataT>
+where
+    IndexT: TecIndex + Display,
+    DataT: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.iter_with_id()
+            .try_for_each(|(id, data)| writeln!(f, "{id}: {data}"))
+    }
+}
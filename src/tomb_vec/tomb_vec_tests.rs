@@ -5,7 +5,7 @@ mod tests {
 
     use stable_id_traits::CastUsize;
 
-    use crate::Tec;
+    use crate::{IndexError, OverlapError, ReinsertError, SealedError, Tec};
 
     #[derive(derive_stable_id::StableId, Debug)]
     struct Id8(u8);
@@ -233,6 +233,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "full-range"))]
     #[should_panic(expected = "exceed storage limit")]
     fn alloc_over_max_capacity() {
         let mut tec = Tec::<u8, u8>::default();
@@ -374,6 +375,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "full-range"))]
     #[should_panic = "exceed storage limit"]
     fn test_remove_then_fill_overflow() {
         // similar to test_remove_then_fill(), but this one alloc() one more item, causing a panic
@@ -506,10 +508,7 @@ mod tests {
 
         assert_eq!(
             HashSet::from([(3, "13".to_owned()), (0, "10".to_owned())]),
-            entities
-                .iter_with_id()
-                .map(|(id, value)| (id, value.to_owned()))
-                .collect(),
+            entities.entries_set(),
         );
 
         entities
@@ -518,10 +517,1234 @@ mod tests {
 
         assert_eq!(
             HashSet::from([(3, "113".to_owned()), (0, "110".to_owned())]),
-            entities
-                .iter_with_id()
-                .map(|(id, value)| (id, value.to_owned()))
-                .collect(),
+            entities.entries_set(),
+        );
+    }
+
+    #[test]
+    fn clone_from() {
+        let source = create_remove_end_2();
+
+        let mut target: Tec<u8, u8> = Tec::with_capacity(1000);
+        (0..100).for_each(|i| {
+            target.alloc(i);
+        });
+        let allocated = target.vec.capacity();
+
+        target.clone_from(&source);
+
+        assert_eq!(format!("{:?}", target), format!("{:?}", source.clone()));
+        assert_eq!(target.vec.capacity(), allocated); // reused the allocation
+        assert_eq!(target.len(), 224);
+    }
+
+    #[test]
+    fn on_change() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::TecEvent;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut tec: Tec<u8, u8> = Default::default();
+
+        let events_ref = events.clone();
+        tec.on_change(Box::new(move |event| {
+            events_ref.lock().unwrap().push(event)
+        }));
+
+        (0..5).for_each(|i| {
+            tec.alloc(i);
+        });
+        tec.remove(1);
+        tec.coalesce(|_, _| {});
+        tec.clear();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                TecEvent::Allocated(0),
+                TecEvent::Allocated(1),
+                TecEvent::Allocated(2),
+                TecEvent::Allocated(3),
+                TecEvent::Allocated(4),
+                TecEvent::Removed(1),
+                TecEvent::Relocated { from: 4, to: 1 },
+                TecEvent::Removed(0),
+                TecEvent::Removed(1),
+                TecEvent::Removed(2),
+                TecEvent::Removed(3),
+            ]
+        );
+
+        // clones don't inherit the observer
+        events.lock().unwrap().clear();
+        tec.clone().alloc(3);
+        assert!(events.lock().unwrap().is_empty());
+
+        tec.clear_observer();
+        tec.alloc(3);
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn on_change_not_sync() {
+        use std::cell::Cell;
+        use std::sync::mpsc;
+
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let (sender, receiver) = mpsc::channel();
+        let mut tec: Tec<u8, u8> = Default::default();
+
+        // `Cell` is `Send` but not `Sync`
+        let removed = Cell::new(0);
+        tec.on_change(Box::new(move |event| {
+            if let crate::TecEvent::Removed(_) = event {
+                removed.set(removed.get() + 1);
+                sender.send(removed.get()).unwrap();
+            }
+        }));
+        assert_sync(&tec);
+
+        let id = tec.alloc(1);
+        tec.remove(id);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), [1]);
+    }
+
+    #[test]
+    fn capacity_accounting() {
+        let mut tec: Tec<u8, u8> = Tec::with_capacity(100);
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+
+        tec.remove(3);
+        tec.remove(5);
+
+        assert_eq!(tec.len(), 8);
+        assert_eq!(tec.dead_count(), 2);
+        assert_eq!(tec.capacity(), 10);
+        assert!(tec.allocated_capacity() >= 100);
+
+        tec.coalesce(|_, _| {});
+        assert_eq!(tec.dead_count(), 0);
+        assert_eq!(tec.capacity(), 8);
+    }
+
+    #[test]
+    fn get_by_usize() {
+        let mut tec: Tec<Id8, char> = Default::default();
+        ['a', 'b', 'c'].into_iter().for_each(|c| {
+            tec.alloc(c);
+        });
+        tec.remove(Id8::cast_from(1));
+
+        assert_eq!(tec.get_by_usize(0), Some(&'a'));
+        assert_eq!(tec.get_by_usize(1), None);
+        assert_eq!(tec.get_by_usize(2), Some(&'c'));
+        assert_eq!(tec.get_by_usize(3), None);
+
+        *tec.get_mut_by_usize(2).expect("slot should be alive") = 'd';
+        assert_eq!(tec[Id8::cast_from(2)], 'd');
+        assert_eq!(tec.get_mut_by_usize(1), None);
+    }
+
+    #[test]
+    fn sorted_ids_by() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        [30, 10, 50, 20, 40, 10].into_iter().for_each(|value| {
+            tec.alloc(value);
+        });
+        tec.remove(2);
+
+        assert_eq!(tec.sorted_ids_by(|a, b| a.cmp(b)), [1, 5, 3, 0, 4]);
+        assert_eq!(tec.sorted_ids_by(|a, b| b.cmp(a)), [4, 0, 3, 1, 5]);
+
+        // ids are untouched
+        assert_eq!(tec[3], 20);
+    }
+
+    #[test]
+    fn reserve_exact() {
+        let mut tec: Tec<u8, u64> = Default::default();
+        tec.alloc(1);
+
+        tec.reserve_exact(9);
+        assert!(tec.allocated_capacity() >= 10);
+        assert_eq!(tec.capacity(), 1);
+        assert_eq!(
+            tec.capacity_bytes(),
+            tec.allocated_capacity() * std::mem::size_of::<crate::Slot<u64, u8>>()
+        );
+
+        tec.reserve(100);
+        assert!(tec.allocated_capacity() >= 101);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tec is sealed")]
+    fn alloc_sealed() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(3);
+        tec.seal();
+        tec.alloc(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tec is sealed")]
+    fn remove_sealed() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(3);
+        tec.seal();
+        tec.remove(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tec is sealed")]
+    fn coalesce_sealed() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(3);
+        tec.remove(0);
+        tec.seal();
+        tec.coalesce(|_, _| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Tec is sealed")]
+    fn sort_storage_sealed() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(3);
+        tec.seal();
+        tec.sort_storage_by(|a, b| a.cmp(b), |_, _| {});
+    }
+
+    #[test]
+    fn try_sealed() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(3);
+        tec.seal();
+        assert_eq!(tec.try_alloc(3), Err(SealedError));
+        assert_eq!(tec.try_remove(1), Err(SealedError));
+        assert_eq!(tec.len(), 3);
+
+        tec.unseal();
+        assert_eq!(tec.try_alloc(3), Ok(3));
+        assert_eq!(tec.try_remove(1), Ok(Some(0)));
+        assert_eq!(tec.try_remove(1), Ok(None));
+    }
+
+    #[test]
+    fn unseal() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(3);
+        tec.seal();
+        assert!(tec.is_sealed());
+
+        tec[1] = 5; // mutating items is fine
+        assert!(tec.clone().is_sealed());
+
+        tec.unseal();
+        assert_eq!(tec.remove(1), 5);
+        assert_eq!(tec.alloc(6), 1);
+    }
+
+    #[test]
+    fn swap() {
+        let mut tec: Tec<u8, char> = Default::default();
+        ['a', 'b', 'c'].into_iter().for_each(|c| {
+            tec.alloc(c);
+        });
+
+        tec.swap(0, 2);
+        assert_eq!(tec[0], 'c');
+        assert_eq!(tec[1], 'b');
+        assert_eq!(tec[2], 'a');
+
+        tec.swap(1, 1);
+        assert_eq!(tec[1], 'b');
+    }
+
+    #[test]
+    #[should_panic(expected = "swapping a dead item")]
+    fn swap_dead() {
+        let mut tec: Tec<u8, char> = Default::default();
+        ['a', 'b', 'c'].into_iter().for_each(|c| {
+            tec.alloc(c);
+        });
+        tec.remove(1);
+
+        tec.swap(0, 1);
+    }
+
+    #[test]
+    fn live_ids_cached() {
+        let mut tec = create_remove_end_2();
+
+        let expected: Vec<_> = tec.iter_with_id().map(|(id, _)| id).collect();
+        assert_eq!(tec.live_ids_cached(), expected);
+        assert_eq!(tec.clone().live_ids_cached(), expected);
+
+        let id = tec.alloc(0);
+        assert!(tec.live_ids_cached().contains(&id));
+
+        tec.remove(0);
+        assert!(!tec.live_ids_cached().contains(&0));
+
+        tec.coalesce(|_, _| {});
+        let expected: Vec<_> = (0..tec.len() as u8).collect();
+        assert_eq!(tec.live_ids_cached(), expected);
+
+        tec.clear();
+        assert!(tec.live_ids_cached().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "full-range")]
+    fn alloc_full_range() {
+        let mut tec = Tec::<u8, u8>::default();
+        (0..=u8::MAX).for_each(|val| {
+            assert_eq!(tec.alloc(val), val);
+        });
+        assert_eq!(tec.len(), 256);
+
+        assert_eq!(tec.remove(u8::MAX), u8::MAX);
+        assert_eq!(tec.remove(3), 3);
+        assert_eq!(tec.alloc(3), 3);
+        assert_eq!(tec.alloc(u8::MAX), u8::MAX);
+
+        tec.remove(10);
+        tec.remove(20);
+
+        let mut records = Vec::new();
+        tec.coalesce(|old_id, new_id| records.push((old_id, new_id)));
+        assert_eq!(records.len(), 2);
+        assert!(records.contains(&(u8::MAX, 10)));
+        assert_eq!(tec.len(), 254);
+    }
+
+    #[test]
+    #[cfg(feature = "full-range")]
+    #[should_panic(expected = "exceed storage limit")]
+    fn alloc_over_full_range() {
+        let mut tec = Tec::<u8, u8>::default();
+        (0..=u8::MAX).for_each(|val| {
+            tec.alloc(val);
+        });
+        tec.alloc(0);
+    }
+
+    #[test]
+    fn clone_skeleton() {
+        let tec = create_remove_end_2();
+        let mut skeleton = tec.clone_skeleton();
+
+        assert_eq!(skeleton.len(), tec.len());
+        assert_eq!(skeleton.capacity(), tec.capacity());
+        assert_eq!(skeleton.next_free, tec.next_free);
+        assert!(skeleton
+            .iter_with_id()
+            .map(|(id, data)| (id, *data))
+            .eq(tec.iter_with_id().map(|(id, _)| (id, 0))));
+
+        // the free list is intact, so both would allocate the same ids
+        let mut tec = tec;
+        (0..10).for_each(|i| assert_eq!(skeleton.alloc(i), tec.alloc(i)));
+    }
+
+    #[test]
+    fn iter_positions() {
+        let mut tec: Tec<Id8, char> = Default::default();
+        ['a', 'b', 'c'].into_iter().for_each(|c| {
+            tec.alloc(c);
+        });
+        tec.remove(Id8::cast_from(1));
+
+        assert_eq!(
+            tec.iter_positions().collect::<Vec<_>>(),
+            [(0, Id8::cast_from(0), &'a'), (2, Id8::cast_from(2), &'c')]
+        );
+    }
+
+    #[test]
+    fn alloc_at_end() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+        [2, 5, 7].into_iter().for_each(|i| {
+            tec.remove(i);
+        });
+
+        // ids keep increasing, even though there are dead slots
+        assert_eq!(tec.alloc_at_end(10), 10);
+        assert_eq!(tec.alloc_at_end(11), 11);
+        assert_eq!(tec.dead_count(), 3);
+
+        // the free list is untouched, so alloc() still fills the dead slots
+        let mut ids: Vec<_> = (0..3).map(|i| tec.alloc(i)).collect();
+        ids.sort();
+        assert_eq!(ids, [2, 5, 7]);
+        assert_eq!(tec.alloc(12), 12);
+        assert_eq!(tec.len(), tec.capacity());
+    }
+
+    #[test]
+    fn retain_compacting() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        (0..20).for_each(|i| {
+            tec.alloc(i);
+        });
+        [3, 8, 9].into_iter().for_each(|i| {
+            tec.remove(i);
+        });
+
+        let mut moves = Vec::new();
+        tec.retain_compacting(
+            |id, data| {
+                assert_eq!(id, *data);
+                data % 2 == 0
+            },
+            |old_id, new_id| moves.push((old_id, new_id)),
+        );
+
+        let expected: Vec<_> = (0..20).filter(|i| i % 2 == 0 && *i != 8).collect();
+        assert_eq!(tec.len(), expected.len());
+        assert_eq!(tec.capacity(), expected.len());
+        assert!(tec.iter().eq(&expected));
+
+        // every survivor is reported where it moved to, except those that are already in place
+        assert!(moves
+            .iter()
+            .all(|(old_id, new_id)| tec[*new_id] == *old_id && old_id > new_id));
+        assert_eq!(moves.len(), expected.len() - 1); // only 0 stays in place
+
+        // compact, so new items are appended
+        assert_eq!(tec.alloc(100), expected.len() as u8);
+    }
+
+    #[test]
+    fn retain_compacting_none() {
+        let mut tec = create_remove_end_2();
+        tec.retain_compacting(|_, _| false, |_, _| unreachable!());
+
+        assert!(tec.is_empty());
+        assert_eq!(tec.capacity(), 0);
+        assert_eq!(tec.alloc(0), 0);
+    }
+
+    #[test]
+    fn display() {
+        let tec: Tec<u8, u8> = Default::default();
+        assert_eq!(tec.to_string(), "");
+
+        let tec = create_remove_end_2();
+        let expected: String = tec
+            .iter_with_id()
+            .map(|(id, data)| format!("{id}: {data}\n"))
+            .collect();
+        assert_eq!(tec.to_string(), expected);
+    }
+
+    #[test]
+    fn sort_storage_by() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        [5, 3, 9, 3, 0, 7].into_iter().for_each(|val| {
+            tec.alloc(val);
+        });
+        let before = tec.clone();
+
+        let mut mapping = HashMap::new();
+        tec.sort_storage_by(
+            |a, b| a.cmp(b),
+            |old_id, new_id| {
+                assert!(mapping.insert(old_id, new_id).is_none());
+            },
+        );
+
+        assert!(tec.iter().eq(&[0, 3, 3, 5, 7, 9]));
+        before.iter_with_id().for_each(|(old_id, data)| {
+            let new_id = mapping.get(&old_id).copied().unwrap_or(old_id);
+            assert_eq!(tec[new_id], *data);
+        });
+
+        // stable: the first 3 stays ahead of the second one
+        assert!(!mapping.contains_key(&1));
+        assert_eq!(mapping[&3], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "sorting the storage with dead slots")]
+    fn sort_storage_by_dead_slots() {
+        let mut tec = create_remove_end_2();
+        tec.sort_storage_by(|a, b| a.cmp(b), |_, _| {});
+    }
+
+    #[test]
+    fn is_compact() {
+        let mut tec = create_remove_end_2();
+        assert!(!tec.is_compact());
+
+        tec.coalesce(|_, _| {});
+        assert!(tec.is_compact());
+        tec.assert_compact();
+
+        assert!(Tec::<u8, u8>::default().is_compact());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tec is not compact")]
+    fn assert_compact() {
+        create_remove_end_2().assert_compact();
+    }
+
+    #[test]
+    fn free_list() {
+        let mut tec = create_remove_end_2();
+        let free_list: Vec<_> = tec.free_list().collect();
+
+        let mut dead_ids = free_list.clone();
+        dead_ids.sort();
+        assert!(dead_ids.iter().all(|id| tec.get(*id).is_none()));
+        assert_eq!(dead_ids.len(), tec.dead_count());
+
+        // the free list tells which ids get reused next
+        free_list
+            .iter()
+            .for_each(|id| assert_eq!(tec.alloc(0), *id));
+        assert_eq!(tec.free_list().count(), 0);
+    }
+
+    #[test]
+    fn chunks() {
+        let tec = create_remove_end_2();
+        let chunks: Vec<_> = tec.chunks(16).collect();
+
+        assert_eq!(chunks.len(), tec.len().div_ceil(16));
+        assert!(chunks.iter().rev().skip(1).all(|chunk| chunk.len() == 16));
+        assert!(chunks.into_iter().flatten().eq(tec.iter_with_id()));
+
+        assert_eq!(Tec::<u8, u8>::default().chunks(3).count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk size must be non-zero")]
+    fn chunks_zero() {
+        let _ = create_remove_end_2().chunks(0);
+    }
+
+    #[test]
+    fn take() {
+        let mut tec = Tec::<u8, u8>::default();
+        assert_eq!(tec.take(0), None);
+
+        let mut tec = create_remove_end_2();
+        let len = tec.len();
+        assert_eq!(tec.take(15), None);
+        assert_eq!(tec.take(u8::MAX), None);
+        assert_eq!(tec.len(), len);
+
+        assert_eq!(tec.take(16), Some(16));
+        assert_eq!(tec.len(), len - 1);
+        assert!(tec.check_consistency());
+        assert_eq!(tec.alloc(100), 16); // the slot is reused first
+    }
+
+    #[test]
+    fn coalesce_stats() {
+        let mut tec = create_remove_end_2();
+        let before_capacity = tec.capacity();
+        let dead_count = tec.dead_count();
+
+        let mut moved = 0;
+        let stats = tec.coalesce_stats(|_, _| moved += 1);
+
+        assert_eq!(stats.moved, moved);
+        assert_eq!(stats.reclaimed, dead_count);
+        assert_eq!(stats.before_capacity, before_capacity);
+        assert_eq!(stats.after_capacity, tec.len());
+
+        // nothing left to do
+        let stats = tec.coalesce_stats(|_, _| unreachable!());
+        assert_eq!((stats.moved, stats.reclaimed), (0, 0));
+        assert_eq!(stats.before_capacity, stats.after_capacity);
+    }
+
+    #[test]
+    fn resize_with() {
+        let mut tec = create_remove_end_2();
+        let dead_ids: HashSet<_> = tec.free_list().collect();
+
+        // growing fills the dead slots first
+        let len = tec.len();
+        tec.resize_with(len + dead_ids.len(), || 0);
+        assert!(tec.is_compact());
+        assert!(dead_ids.iter().all(|id| tec[*id] == 0));
+
+        // shrinking removes the highest ids
+        tec.resize_with(100, || unreachable!());
+        assert_eq!(tec.len(), 100);
+        assert_eq!(tec.capacity(), 100);
+        assert!(tec
+            .iter_with_id()
+            .all(|(id, data)| dead_ids.contains(&id) || id == *data));
+
+        tec.remove(10);
+        tec.remove(50);
+        tec.resize_with(40, || unreachable!());
+        assert_eq!(tec.capacity(), 41);
+        assert_eq!(tec.free_list().collect::<Vec<_>>(), [10]);
+
+        tec.resize_with(0, || unreachable!());
+        assert!(tec.is_empty());
+        assert_eq!(tec.capacity(), 0);
+        assert_eq!(tec.alloc(0), 0);
+    }
+
+    #[test]
+    fn first_and_last() {
+        let mut tec = Tec::<u8, u8>::default();
+        assert_eq!(tec.first(), None);
+        assert_eq!(tec.last_mut(), None);
+
+        (0..10).for_each(|i| {
+            tec.alloc(i * 2);
+        });
+        tec.remove(0);
+        tec.remove(9);
+
+        assert_eq!(tec.first(), Some((1, &2)));
+        assert_eq!(tec.last(), Some((8, &16)));
+
+        *tec.first_mut().unwrap().1 = 100;
+        *tec.last_mut().unwrap().1 = 200;
+        assert_eq!(tec[1], 100);
+        assert_eq!(tec[8], 200);
+    }
+
+    #[test]
+    fn retain_ids() {
+        let mut tec = create_remove_end_2();
+        let keep: HashSet<_> = (0..u8::MAX).filter(|i| i % 5 == 0).collect();
+        let expected: Vec<_> = tec
+            .iter_with_id()
+            .filter(|(id, _)| keep.contains(id))
+            .map(|(id, data)| (id, *data))
+            .collect();
+
+        tec.retain_ids(&keep);
+
+        // the survivors keep their ids
+        assert!(tec
+            .iter_with_id()
+            .map(|(id, data)| (id, *data))
+            .eq(expected.iter().copied()));
+        assert!(tec.check_consistency());
+    }
+
+    #[test]
+    fn coalesce_budgeted() {
+        let mut budgeted = create_remove_end_2();
+        let mut full = budgeted.clone();
+
+        let mut full_moves = Vec::new();
+        full.coalesce(|old_id, new_id| full_moves.push((old_id, new_id)));
+
+        let mut budgeted_moves = Vec::new();
+        while !budgeted.coalesce_budgeted(3, |old_id, new_id| budgeted_moves.push((old_id, new_id)))
+        {
+            assert!(budgeted.check_consistency());
+        }
+
+        // same result as coalescing in one go
+        assert_eq!(budgeted_moves, full_moves);
+        assert!(budgeted.iter_with_id().eq(full.iter_with_id()));
+        assert!(budgeted.coalesce_budgeted(0, |_, _| unreachable!()));
+    }
+
+    #[test]
+    fn dead_ranges() {
+        let mut tec = Tec::<u8, u8>::populate_defaults(20);
+        assert_eq!(tec.dead_ranges().count(), 0);
+
+        [0, 1, 2, 9, 12, 11, 10, 19].into_iter().for_each(|id| {
+            tec.remove(id);
+        });
+
+        assert_eq!(tec.dead_ranges().collect::<Vec<_>>(), [0..3, 9..13, 19..20]);
+        assert_eq!(
+            tec.dead_ranges().map(|range| range.len()).sum::<usize>(),
+            tec.dead_count()
         );
     }
+
+    #[test]
+    fn view() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+        tec.remove(4);
+        tec.remove(7);
+
+        let view = tec.view(|data| data % 2 == 0);
+        assert_eq!(view.get(2), Some(&2));
+        assert_eq!(view.get(3), None); // alive, but filtered out
+        assert_eq!(view.get(4), None); // dead
+        assert!(!view.contains(9));
+        assert!(view.iter().eq(&[0, 2, 6, 8]));
+        assert!(view
+            .iter_with_id()
+            .rev()
+            .eq([(8, &8), (6, &6), (2, &2), (0, &0)]));
+    }
+
+    #[test]
+    fn get_disjoint_mut_vec() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+        tec.remove(5);
+
+        let refs = tec.get_disjoint_mut_vec(&[7, 0, 3]).unwrap();
+        assert_eq!(refs, [&7, &0, &3]);
+        refs.into_iter().for_each(|data| *data += 100);
+        assert_eq!(tec[7], 107);
+        assert_eq!(tec[0], 100);
+        assert_eq!(tec[3], 103);
+
+        assert_eq!(tec.get_disjoint_mut_vec(&[]), Some(vec![]));
+        assert!(tec.get_disjoint_mut_vec(&[1, 2, 1]).is_none()); // duplicated
+        assert!(tec.get_disjoint_mut_vec(&[1, 5]).is_none()); // dead
+        assert!(tec.get_disjoint_mut_vec(&[1, 200]).is_none()); // out of bound
+
+        let refs = tec.get_disjoint_mut_vec(&[9, 8, 4, 6]).unwrap();
+        assert_eq!(refs, [&9, &8, &4, &6]);
+    }
+
+    #[test]
+    fn reinsert() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+
+        // unlink from the head, the middle and the tail of the free list
+        [2, 5, 7, 8].into_iter().for_each(|id| {
+            tec.remove(id);
+        });
+        for id in [5, 8, 2] {
+            assert_eq!(tec.reinsert(id, id), Ok(()));
+            assert!(tec.check_consistency());
+        }
+        assert_eq!(tec.dead_count(), 1);
+        assert_eq!(tec.reinsert(5, 0), Err(ReinsertError::Occupied));
+        assert_eq!(tec.alloc(7), 7);
+        assert_eq!(tec.alloc(10), 10);
+
+        assert_eq!(tec.reinsert(12, 12), Err(ReinsertError::OutOfBounds));
+        assert_eq!(tec.reinsert(11, 11), Ok(()));
+        assert!(tec.iter().copied().eq(0..12));
+        assert_eq!(tec.total_allocated(), 16);
+    }
+
+    #[test]
+    fn alloc_mut() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        (0..5).for_each(|i| {
+            tec.alloc(i);
+        });
+        tec.remove(2);
+
+        let (id, data) = tec.alloc_mut(20);
+        assert_eq!(id, 2);
+        *data += 1;
+        assert_eq!(tec[2], 21);
+
+        let (id, data) = tec.alloc_mut(50);
+        assert_eq!((id, *data), (5, 50));
+        assert_eq!(tec.len(), 6);
+    }
+
+    #[test]
+    fn entries_set() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        assert!(tec.entries_set().is_empty());
+
+        (0..10).for_each(|i| {
+            tec.alloc(i * 2);
+        });
+        [1, 3, 9].into_iter().for_each(|id| {
+            tec.remove(id);
+        });
+
+        let expected: HashSet<_> = [0, 2, 4, 5, 6, 7, 8].map(|id| (id, id * 2)).into();
+        assert_eq!(tec.entries_set(), expected);
+        assert_eq!(
+            tec.entries_ref_set(),
+            expected.iter().map(|(id, data)| (*id, data)).collect()
+        );
+    }
+
+    #[test]
+    fn size_hint() {
+        let mut tec: Tec<u8, u8> = (0..10).collect();
+        assert_eq!(tec.len(), 10);
+        [2, 3, 7].into_iter().for_each(|id| {
+            tec.remove(id);
+        });
+
+        let mut iter = tec.iter_with_id();
+        assert_eq!(iter.len(), 7);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.count(), 5);
+
+        assert_eq!(tec.iter().len(), 7);
+        assert_eq!(tec.iter_positions().len(), 7);
+        assert_eq!(tec.iter_mut().len(), 7);
+        assert_eq!(tec.iter_mut_with_id().len(), 7);
+        assert_eq!(tec.clone().into_iter_with_id().len(), 7);
+    }
+
+    #[test]
+    fn extend() {
+        let mut tec: Tec<u8, u8> = (0..10).collect();
+        tec.remove(4);
+        tec.remove(8);
+
+        tec.extend([80, 40, 100]); // the most recently freed slot is reused first
+        assert_eq!(tec.capacity(), 11);
+        assert_eq!(tec[4], 40);
+        assert_eq!(tec[8], 80);
+        assert_eq!(tec[10], 100);
+
+        tec.extend(vec![0; 50]);
+        assert!(tec.allocated_capacity() >= 61);
+        assert_eq!(tec.len(), 61);
+    }
+
+    #[cfg(feature = "debug-tracking")]
+    #[test]
+    fn generation() {
+        let mut tec: Tec<u8, u8> = (0..10).collect();
+        assert_eq!(tec.generation(3), 0);
+
+        tec.remove(3);
+        assert_eq!(tec.generation(3), 1);
+        assert_eq!(tec.alloc(30), 3);
+        assert_eq!(tec.get_checked(3, 1), Some(&30));
+
+        // relocated items leave their old slots behind
+        tec.remove(1);
+        tec.coalesce(|_, _| {});
+        assert_eq!(tec.generation(1), 1);
+        assert_eq!(tec.generation(9), 1);
+
+        tec.retain_compacting(|_, data| *data != 0, |_, _| {});
+        assert!((0..9).all(|id| tec.generation(id) > 0));
+
+        tec.clear();
+        assert_eq!(tec.generation(7), 2);
+    }
+
+    #[cfg(feature = "debug-tracking")]
+    #[test]
+    #[should_panic(expected = "use after remove")]
+    fn get_checked_stale() {
+        let mut tec: Tec<u8, u8> = Default::default();
+        let id = tec.alloc(1);
+        let generation = tec.generation(id);
+
+        tec.remove(id);
+        tec.alloc(2);
+        let _ = tec.get_checked(id, generation);
+    }
+
+    #[test]
+    fn extend_from_disjoint() {
+        // two halves of the id space, filled separately
+        let mut low: Tec<u8, u8> = (0..10).collect();
+        let mut high: Tec<u8, u8> = (0..20).collect();
+        (0..15).for_each(|id| {
+            high.remove(id);
+        });
+        low.remove(3);
+
+        low.extend_from_disjoint(high).unwrap();
+        assert_eq!(low.len(), 14);
+        assert_eq!(low.capacity(), 20);
+        assert!(low.iter_with_id().all(|(id, data)| id == *data));
+
+        // gaps are reused smallest first
+        assert!(low.free_list().eq([3, 10, 11, 12, 13, 14]));
+        assert_eq!(low.alloc(3), 3);
+
+        let mut colliding: Tec<u8, u8> = Default::default();
+        colliding.resize_with(17, Default::default);
+        (0..16).for_each(|id| {
+            colliding.remove(id);
+        });
+        assert_eq!(
+            low.extend_from_disjoint(colliding),
+            Err(OverlapError { id: 16 })
+        );
+        assert_eq!(low.len(), 15);
+
+        // empty
+        low.extend_from_disjoint(Default::default()).unwrap();
+        assert_eq!(low.len(), 15);
+    }
+
+    #[test]
+    fn split_at_mut() {
+        let mut tec: Tec<u8, u8> = (0..10).collect();
+        tec.remove(2);
+        tec.remove(7);
+
+        let (mut low, mut high) = tec.split_at_mut(4);
+        assert_eq!((low.range(), high.range()), (0..4, 4..10));
+        assert_eq!(low.get(2), None);
+        assert_eq!(low.get(4), None); // belongs to the other half
+        assert_eq!(high.get(4), Some(&4));
+        *high.get_mut(9).unwrap() = 90;
+        assert!(low.iter_mut_with_id().map(|(id, _)| id).eq([0, 1, 3]));
+        assert!(high.iter_mut().rev().map(|data| *data).eq([90, 8, 6, 5, 4]));
+
+        let (empty, all) = tec.split_at_mut(0);
+        assert_eq!((empty.range(), all.range()), (0..0, 0..10));
+    }
+
+    #[test]
+    fn iterators_agree_on_sparse_storage() {
+        let mut tec: Tec<u16, u16> = (0..300).collect();
+        (0..300)
+            .filter(|id| id % 7 != 0 || *id == 0)
+            .for_each(|id| {
+                tec.remove(id);
+            });
+        let expected: Vec<_> = tec.iter_with_id().map(|(id, data)| (id, *data)).collect();
+
+        let mut front_and_back = Vec::new();
+        {
+            let mut iter = tec.iter_mut_with_id();
+            while let (Some(front), back) = (iter.next(), iter.next_back()) {
+                front_and_back.push((front.0, *front.1));
+                front_and_back.extend(back.map(|(id, data)| (id, *data)));
+            }
+        }
+        front_and_back.sort_unstable();
+        assert_eq!(front_and_back, expected);
+
+        assert!(tec
+            .iter_mut()
+            .map(|data| *data)
+            .eq(expected.iter().map(|(_, data)| *data)));
+        assert!(tec
+            .iter_from(100)
+            .map(|(id, data)| (id, *data))
+            .eq(expected.iter().copied().filter(|(id, _)| *id >= 100)));
+
+        let (_, mut high) = tec.split_at_mut(70);
+        assert!(high
+            .iter_mut_with_id()
+            .rev()
+            .map(|(id, data)| (id, *data))
+            .eq(expected.iter().copied().filter(|(id, _)| *id >= 70).rev()));
+
+        assert!(tec.into_iter_with_id().rev().eq(expected.into_iter().rev()));
+    }
+
+    #[test]
+    #[should_panic(expected = "splitting beyond the capacity")]
+    fn split_at_mut_out_of_bound() {
+        let mut tec: Tec<u8, u8> = (0..10).collect();
+        let _ = tec.split_at_mut(11);
+    }
+
+    #[test]
+    fn shrink_to() {
+        let mut tec: Tec<u8, u64> = Default::default();
+        (0..4).for_each(|i| {
+            tec.alloc(i);
+        });
+        tec.reserve(100);
+
+        tec.shrink_to(20);
+        assert!(tec.allocated_capacity() >= 20);
+        assert!(tec.allocated_capacity() < 104);
+
+        // never drops below the slots in use
+        tec.shrink_to(0);
+        assert!(tec.allocated_capacity() >= 4);
+        assert_eq!(tec.capacity(), 4);
+        assert_eq!(tec.len(), 4);
+    }
+
+    #[test]
+    fn at() {
+        fn add_one(tec: &mut Tec<u8, u32>, index: u8) -> Result<u32, IndexError> {
+            let data = tec.at_mut(index)?;
+            *data += 1;
+            Ok(*data)
+        }
+
+        let mut tec: Tec<u8, u32> = Default::default();
+        (0..5).for_each(|i| {
+            tec.alloc(i);
+        });
+        tec.remove(3);
+
+        assert_eq!(add_one(&mut tec, 2), Ok(3));
+        assert_eq!(tec.at(2), Ok(&3));
+        assert_eq!(add_one(&mut tec, 3), Err(IndexError::Dead));
+        assert_eq!(add_one(&mut tec, 5), Err(IndexError::OutOfBounds));
+        assert_eq!(tec.at(255), Err(IndexError::OutOfBounds));
+        assert_eq!(IndexError::Dead.to_string(), "the slot is dead");
+    }
+
+    #[test]
+    fn coalesce_order() {
+        let mut entities = create_remove_end_1();
+
+        let mut records = Vec::new();
+        entities.coalesce(|old_id, new_id| records.push((old_id, new_id)));
+        assert_eq!(records, [(250, 15), (249, 27)]);
+
+        let mut entities = create_remove_end_2();
+
+        let mut records = Vec::new();
+        entities.coalesce(|old_id, new_id| records.push((old_id, new_id)));
+
+        // the holes are filled in increasing order, each by the living item with the highest id
+        let (old_ids, new_ids): (Vec<_>, Vec<_>) = records.iter().copied().unzip();
+        assert!(new_ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(old_ids.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(new_ids, [15, 25, 27, 30, 34, 35]);
+    }
+
+    #[test]
+    fn fill_from() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+        [7, 2, 5].into_iter().for_each(|i| {
+            tec.remove(i);
+        });
+        let free_list: Vec<_> = tec.free_list().collect();
+
+        let ids = tec.fill_from(100..105);
+        assert_eq!(ids[..3], free_list);
+        assert_eq!(ids[3..], [10, 11]);
+        assert_eq!(tec.len(), 12);
+        assert_eq!(tec.dead_count(), 0);
+        ids.iter()
+            .zip(100..)
+            .for_each(|(&id, data)| assert_eq!(tec[id], data));
+
+        assert!(tec.fill_from(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn iter_all() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        (0..10).for_each(|i| {
+            tec.alloc(i);
+        });
+        [1, 4, 9].into_iter().for_each(|i| {
+            tec.remove(i);
+        });
+
+        assert_eq!(tec.iter_all().len(), tec.capacity());
+        tec.iter_all().enumerate().for_each(|(position, data)| {
+            assert_eq!(data, tec.get(position as u8));
+            assert_eq!(data, tec.option_at(position));
+        });
+        assert_eq!(tec.iter_all().flatten().count(), tec.len());
+        assert_eq!(tec.iter_all().next_back(), Some(None));
+        assert_eq!(tec.option_at(10), None);
+    }
+
+    #[test]
+    fn coalesce_and_shrink() {
+        let mut tec: Tec<u8, u64> = Default::default();
+        (0..200).for_each(|i| {
+            tec.alloc(i);
+        });
+        (0..150).for_each(|i| {
+            tec.remove(i);
+        });
+
+        let mut moved = 0;
+        tec.coalesce_and_shrink(|_, _| moved += 1);
+        assert_eq!(moved, 50);
+        assert_eq!(tec.capacity(), 50);
+        assert!(tec.allocated_capacity() < 200);
+        assert!(tec.iter().copied().eq((150..200).rev()));
+    }
+
+    #[test]
+    fn remaining_capacity() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        let max_slots = tec.remaining_capacity();
+        tec.extend(0..10);
+        tec.remove(3);
+        assert_eq!(tec.remaining_capacity(), max_slots - 10);
+
+        // exactly fills up every id
+        let room = tec.remaining_capacity() + tec.dead_count();
+        tec.fill_from(0..room as u32);
+        assert_eq!(tec.remaining_capacity(), 0);
+        assert_eq!(tec.len(), max_slots);
+    }
+
+    #[test]
+    #[should_panic(expected = "u8 can only address")]
+    fn fill_from_over_capacity() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        tec.extend(0..10);
+        tec.fill_from(0..300);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot allocate 300 more items")]
+    fn extend_over_capacity() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        tec.extend(0..300);
+    }
+
+    #[test]
+    fn iter_from() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        (0..30).for_each(|i| {
+            tec.alloc(i);
+        });
+        (0..30).step_by(4).for_each(|i| {
+            tec.remove(i);
+        });
+
+        (0..=u8::MAX).for_each(|start| {
+            let expected: Vec<_> = tec.iter_with_id().filter(|(id, _)| *id >= start).collect();
+            assert!(tec.iter_from(start).eq(expected));
+        });
+
+        // resume in chunks
+        let mut resumed = Vec::new();
+        let mut cursor = 0;
+        while let Some(last) = tec
+            .iter_from(cursor)
+            .take(5)
+            .map(|(id, data)| {
+                resumed.push(*data);
+                id
+            })
+            .last()
+        {
+            cursor = last + 1;
+        }
+        assert!(resumed.iter().eq(tec.iter()));
+    }
+
+    #[test]
+    fn zero_sized() {
+        let mut tec: Tec<u16, ()> = Default::default();
+        let ids: Vec<_> = (0..100).map(|_| tec.insert_marker()).collect();
+        assert!(ids.iter().copied().eq(0..100));
+
+        (0..100).step_by(3).for_each(|i| tec.remove(i));
+        assert_eq!(tec.len(), 66);
+        assert_eq!(tec.iter().count(), 66);
+        assert!(tec
+            .iter_with_id()
+            .map(|(id, _)| id)
+            .eq((0..100).filter(|&i| tec.get(i).is_some())));
+
+        // relocations are still reported even though the payload has no size
+        let mut records = Vec::new();
+        tec.coalesce(|old_id, new_id| records.push((old_id, new_id)));
+        assert_eq!(records.len(), 22);
+        assert!(records.iter().all(|&(old_id, new_id)| new_id < old_id));
+        assert!(records.iter().all(|&(_, new_id)| new_id % 3 == 0));
+        assert_eq!(tec.len(), 66);
+        assert_eq!(tec.capacity(), 66);
+        assert!(tec.iter_with_id().map(|(id, _)| id).eq(0..66));
+
+        let id = tec.insert_marker();
+        assert_eq!(id, 66);
+        assert_eq!(tec.take(id), Some(()));
+        assert_eq!(tec.take(id), None);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn par_coalesce() {
+        let mut tec: Tec<u16, u32> = Default::default();
+        tec.extend(0..2000);
+        (0..2000).filter(|i| i % 3 != 1).for_each(|i| {
+            tec.remove(i);
+        });
+        (600..700).for_each(|i| {
+            tec.alloc(i);
+        });
+
+        let mut expected = tec.clone();
+        let mut expected_moves = Vec::new();
+        expected.coalesce(|old_id, new_id| expected_moves.push((old_id, new_id)));
+
+        // force the threads even on a single core
+        let moves = std::sync::Mutex::new(Vec::new());
+        tec.coalesce_in_threads(4, 1, |old_id, new_id| {
+            moves.lock().unwrap().push((old_id, new_id))
+        });
+
+        let mut moves = moves.into_inner().unwrap();
+        moves.sort_unstable_by_key(|&(_, new_id)| new_id);
+        assert_eq!(moves, expected_moves);
+        assert!(tec.iter_with_id().eq(expected.iter_with_id()));
+        assert_eq!(tec.capacity(), expected.capacity());
+        assert_eq!(tec.dead_count(), 0);
+
+        let id = tec.alloc(12345);
+        assert_eq!(id as usize, expected.capacity());
+    }
+
+    #[test]
+    fn clear_and_shrink() {
+        let mut tec: Tec<u8, u32> = Default::default();
+        tec.extend(0..100);
+        tec.remove(50);
+        let allocated = tec.allocated_capacity();
+
+        tec.clear();
+        assert!(tec.is_empty());
+        assert_eq!(tec.capacity(), 0);
+        assert_eq!(tec.allocated_capacity(), allocated);
+
+        tec.extend(0..100);
+        tec.clear_and_shrink();
+        assert!(tec.is_empty());
+        assert_eq!(tec.capacity(), 0);
+        assert_eq!(tec.allocated_capacity(), 0);
+
+        assert_eq!(tec.alloc(7), 0);
+    }
+
+    #[test]
+    fn iter_with_id_order() {
+        let mut tec: Tec<u16, u32> = Default::default();
+        tec.extend(0..500);
+        (0..500).step_by(3).chain(100..200).for_each(|i| {
+            tec.take(i);
+        });
+        // refill some holes, so that the allocation order differs from the id order
+        (0..40).for_each(|i| {
+            tec.alloc(1000 + i);
+        });
+
+        let ids: Vec<_> = tec.iter_with_id().map(|(id, _)| id).collect();
+        assert_eq!(ids.len(), tec.len());
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ids.iter().all(|&id| tec.get(id).is_some()));
+
+        let rev_ids: Vec<_> = tec.iter_with_id_rev().map(|(id, _)| id).collect();
+        assert!(rev_ids.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(rev_ids.iter().rev().eq(&ids));
+        assert_eq!(tec.iter_with_id_rev().len(), tec.len());
+    }
 }
@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+use crate::{Slot, TecIndex, TecMutView, TecView};
+
+#[cfg(feature = "fast-iter")]
+use super::live_bits::{LiveBits, LiveSlots};
+
+impl<'a, IndexT, DataT, P> TecView<'a, IndexT, DataT, P>
+where
+    IndexT: TecIndex,
+    P: Fn(&DataT) -> bool,
+{
+    /// Returns `None` if the item is dead or doesn't satisfy the predicate.
+    pub fn get(&self, index: IndexT) -> Option<&'a DataT> {
+        self.tec.get(index).filter(|data| (self.pred)(data))
+    }
+
+    pub fn contains(&self, index: IndexT) -> bool {
+        self.get(index).is_some()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &'a DataT> + '_ {
+        self.tec.iter().filter(|data| (self.pred)(data))
+    }
+
+    pub fn iter_with_id(&self) -> impl DoubleEndedIterator<Item = (IndexT, &'a DataT)> + '_ {
+        self.tec
+            .iter_with_id()
+            .filter(|(_, data)| (self.pred)(data))
+    }
+}
+
+impl<'a, IndexT, DataT> TecMutView<'a, IndexT, DataT>
+where
+    IndexT: TecIndex,
+{
+    pub(crate) fn new(
+        slots: &'a mut [Slot<DataT, IndexT>],
+        offset: usize,
+        #[cfg(feature = "fast-iter")] live: &'a LiveBits,
+    ) -> Self {
+        Self {
+            slots,
+            offset,
+            #[cfg(feature = "fast-iter")]
+            live,
+        }
+    }
+
+    /// The positions of the slots covered by this view.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.slots.len()
+    }
+
+    /// Returns `None` if the item is dead or outside of the view. Ids are the same as in the whole [`crate::Tec`].
+    pub fn get(&self, index: IndexT) -> Option<&DataT> {
+        let position = index.cast_to().checked_sub(self.offset)?;
+        match self.slots.get(position)? {
+            Slot::Alive(data) => Some(data),
+            Slot::Dead { .. } => None,
+        }
+    }
+
+    /// Mutable version of [`Self::get()`].
+    pub fn get_mut(&mut self, index: IndexT) -> Option<&mut DataT> {
+        let position = index.cast_to().checked_sub(self.offset)?;
+        match self.slots.get_mut(position)? {
+            Slot::Alive(data) => Some(data),
+            Slot::Dead { .. } => None,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut DataT> {
+        self.iter_mut_with_id().map(|(_, data)| data)
+    }
+
+    pub fn iter_mut_with_id(&mut self) -> impl DoubleEndedIterator<Item = (IndexT, &mut DataT)> {
+        let offset = self.offset;
+
+        #[cfg(not(feature = "fast-iter"))]
+        let iter =
+            self.slots
+                .iter_mut()
+                .enumerate()
+                .filter_map(move |(position, slot)| match slot {
+                    Slot::Alive(data) => Some((IndexT::cast_from(offset + position), data)),
+                    Slot::Dead { .. } => None,
+                });
+
+        #[cfg(feature = "fast-iter")]
+        let positions = self.live.positions_in(self.range());
+        #[cfg(feature = "fast-iter")]
+        let iter =
+            LiveSlots::new(self.slots.iter_mut(), positions, offset).map(|(position, slot)| {
+                match slot {
+                    Slot::Alive(data) => (IndexT::cast_from(position), data),
+                    Slot::Dead { .. } => unreachable!("dead slot marked as alive"),
+                }
+            });
+
+        iter
+    }
+}
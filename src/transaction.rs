@@ -0,0 +1,181 @@
+use std::{hash::BuildHasher, mem};
+
+use crate::{Entities, EntitiesIndex, EntitiesTxn, Handle, TxnOp};
+
+impl<IndexT, DataT, S> Entities<IndexT, DataT, S>
+where
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
+{
+    /**
+    Apply a batch of changes all-or-nothing: if `f` returns `Err`, every change made through the [`EntitiesTxn`]
+    is undone in reverse order, including the ids issued by the transaction, which will be issued again.
+    The ids of the surviving items are unaffected, though the physical layout may differ.
+
+    Note: the changes are not undone if `f` panics.
+
+    ```
+    use stable_id::Entities;
+
+    let mut entities: Entities<u8, i32> = Default::default();
+    let a = entities.alloc(1);
+
+    let result: Result<(), &str> = entities.transaction(|txn| {
+        txn.set(a, 10);
+        txn.alloc(2);
+        if !txn.remove(a) {
+            return Err("missing");
+        }
+        Err("changed my mind")
+    });
+
+    assert_eq!(result, Err("changed my mind"));
+    assert_eq!(entities.len(), 1);
+    assert_eq!(entities[a], 1);
+    ```
+    */
+    pub fn transaction<F, E>(&mut self, f: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut EntitiesTxn<'_, IndexT, DataT, S>) -> Result<(), E>,
+    {
+        let mut txn = EntitiesTxn {
+            checkpoint: self.seq.checkpoint(),
+            entities: self,
+            log: Vec::new(),
+        };
+
+        let result = f(&mut txn);
+        if result.is_err() {
+            txn.rollback();
+        }
+        result
+    }
+}
+
+impl<IndexT, DataT, S> EntitiesTxn<'_, IndexT, DataT, S>
+where
+    IndexT: EntitiesIndex,
+    S: BuildHasher,
+{
+    pub fn alloc(&mut self, data: DataT) -> Handle<IndexT> {
+        let id = self.entities.alloc(data);
+        self.log.push(TxnOp::Alloc(id));
+        id
+    }
+
+    /// Returns whether there was an item to remove.
+    pub fn remove(&mut self, id: Handle<IndexT>) -> bool {
+        match self.entities.remove(id) {
+            Some(data) => {
+                self.log.push(TxnOp::Remove(id, data));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the item of `id` with `data`. Returns whether there was an item to replace.
+    pub fn set(&mut self, id: Handle<IndexT>, data: DataT) -> bool {
+        match self.entities.get_mut(id) {
+            Some(current) => {
+                let old = mem::replace(current, data);
+                self.log.push(TxnOp::Set(id, old));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: Handle<IndexT>) -> Option<&DataT> {
+        self.entities.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    fn rollback(&mut self) {
+        while let Some(op) = self.log.pop() {
+            match op {
+                TxnOp::Alloc(id) => {
+                    self.entities.remove(id).expect("allocated item is gone");
+                }
+                TxnOp::Remove(id, data) => self
+                    .entities
+                    .reinsert(id, data)
+                    .expect("removed item came back"),
+                TxnOp::Set(id, old) => {
+                    *self.entities.get_mut(id).expect("updated item is gone") = old;
+                }
+            }
+        }
+
+        // every id issued since the checkpoint has been taken back
+        self.entities.seq.rollback_to(self.checkpoint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Entities;
+
+    #[test]
+    fn commit() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let a = entities.alloc(1);
+
+        let mut b = None;
+        let result: Result<(), ()> = entities.transaction(|txn| {
+            assert!(txn.set(a, 2));
+            b = Some(txn.alloc(3));
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+
+        let b = b.unwrap();
+        assert_eq!(entities[a], 2);
+        assert_eq!(entities[b], 3);
+    }
+
+    #[test]
+    fn rollback() {
+        let mut entities: Entities<u8, u8> = Default::default();
+        let ids: Vec<_> = (0..50).map(|i| entities.alloc(i)).collect();
+        let before = entities
+            .to_btreemap()
+            .into_iter()
+            .map(|(id, data)| (id, *data))
+            .collect::<Vec<_>>();
+
+        let result = entities.transaction(|txn| {
+            // enough removals to make the collection coalesce itself
+            ids.iter().step_by(3).for_each(|id| {
+                assert!(txn.remove(*id));
+            });
+            (0..10).for_each(|i| {
+                txn.alloc(100 + i);
+            });
+            assert!(txn.set(ids[1], 200));
+            assert!(txn.set(ids[1], 201));
+            assert!(!txn.remove(ids[0]));
+            assert_eq!(txn.len(), 43);
+
+            Err(())
+        });
+
+        assert_eq!(result, Err(()));
+        let after = entities
+            .to_btreemap()
+            .into_iter()
+            .map(|(id, data)| (id, *data))
+            .collect::<Vec<_>>();
+        assert_eq!(after, before);
+
+        // the ids allocated by the aborted transaction are issued again
+        assert_eq!(entities.alloc(0).raw(), 50);
+    }
+}